@@ -0,0 +1,147 @@
+use crate::{Error, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+// Account name is used directly as a directory name, so it has to be safe on every platform.
+
+const MAX_ACCOUNT_NAME_LENGTH: usize = 64; // characters
+const FORBIDDEN_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(transparent)]
+pub(crate) struct AccountName(String);
+
+impl AccountName {
+    // Normalizes the name (NFC, surrounding whitespace) and validates it.
+    pub(crate) fn parse(name: &str) -> Result<Self, Error> {
+        let name = name.trim().nfc().collect::<String>();
+        let bad = |reason: &str| Err(Error::BadAccountName(String::from(reason)));
+
+        let length = name.chars().count();
+        if length == 0 {
+            return bad("Account name is empty.");
+        }
+        if length > MAX_ACCOUNT_NAME_LENGTH {
+            return bad("Account name is too long.");
+        }
+        if name.chars().any(|c| c.is_control() || FORBIDDEN_CHARS.contains(&c)) {
+            return bad("Account name contains forbidden characters.");
+        }
+        if name.starts_with('.') || name.ends_with('.') {
+            return bad("Account name cannot start or end with a dot.");
+        }
+
+        let stem = name.split('.').next().unwrap_or_default().trim_end();
+        if WINDOWS_RESERVED_NAMES
+            .iter()
+            .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+        {
+            return bad("Account name is reserved by the system.");
+        }
+
+        Ok(AccountName(name))
+    }
+
+    // Makes a valid name out of anything, for migrating accounts created before validation.
+    pub(crate) fn sanitize(name: &str) -> Self {
+        let mut cleaned = name
+            .trim()
+            .nfc()
+            .map(|c| {
+                if c.is_control() || FORBIDDEN_CHARS.contains(&c) {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .take(MAX_ACCOUNT_NAME_LENGTH)
+            .collect::<String>()
+            .trim_matches(|c: char| c == '.' || c.is_whitespace())
+            .to_string();
+
+        if cleaned.is_empty() {
+            cleaned = String::from("account");
+        }
+
+        AccountName::parse(&cleaned)
+            .or_else(|_| AccountName::parse(&format!("{}_", cleaned))) // reserved names
+            .unwrap_or_else(|_| AccountName(String::from("account")))
+    }
+
+    // e.g. "alice-2", used to resolve name clashes
+    pub(crate) fn with_suffix(&self, n: usize) -> Self {
+        let suffix = format!("-{}", n);
+        let stem = self
+            .0
+            .chars()
+            .take(MAX_ACCOUNT_NAME_LENGTH - suffix.chars().count())
+            .collect::<String>();
+        AccountName::sanitize(&format!("{}{}", stem, suffix))
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for AccountName {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<std::path::Path> for AccountName {
+    fn as_ref(&self) -> &std::path::Path {
+        std::path::Path::new(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_names() {
+        assert_eq!("alice", AccountName::parse("alice").unwrap().as_str());
+        assert_eq!("Bob Smith", AccountName::parse("  Bob Smith ").unwrap().as_str());
+        assert_eq!("v1.2", AccountName::parse("v1.2").unwrap().as_str());
+        assert_eq!("Łukasz", AccountName::parse("Łukasz").unwrap().as_str());
+        // decomposed "é" gets composed
+        assert_eq!("é", AccountName::parse("e\u{301}").unwrap().as_str());
+    }
+
+    #[test]
+    fn invalid_names() {
+        for name in [
+            "", "   ", ".", "..", "../x", "a/b", "a\\b", "C:", "a\nb", ".hidden", "dot.", "con",
+            "LPT1.txt", "aux ",
+        ] {
+            assert!(AccountName::parse(name).is_err(), "{:?} should be invalid", name);
+        }
+        assert!(AccountName::parse(&"x".repeat(MAX_ACCOUNT_NAME_LENGTH)).is_ok());
+        assert!(AccountName::parse(&"x".repeat(MAX_ACCOUNT_NAME_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn sanitized_names() {
+        assert_eq!("a_b", AccountName::sanitize("a/b").as_str());
+        assert_eq!("hidden", AccountName::sanitize(".hidden").as_str());
+        assert_eq!("con_", AccountName::sanitize("con").as_str());
+        assert_eq!("account", AccountName::sanitize("..").as_str());
+        assert_eq!(
+            MAX_ACCOUNT_NAME_LENGTH,
+            AccountName::sanitize(&"x".repeat(100)).as_str().chars().count()
+        );
+    }
+
+    #[test]
+    fn suffixed_names() {
+        assert_eq!("alice-2", AccountName::parse("alice").unwrap().with_suffix(2).as_str());
+        let long = AccountName::sanitize(&"x".repeat(100)).with_suffix(12);
+        assert_eq!(MAX_ACCOUNT_NAME_LENGTH, long.as_str().chars().count());
+        assert!(long.as_str().ends_with("x-12"));
+    }
+}
@@ -0,0 +1,411 @@
+use crate::network::NetworkSetting;
+use crate::{keys, Error, XorName, DEFAULT_LOG_LEVEL};
+use async_trait::async_trait;
+use futures::lock::Mutex;
+use safeapi::Safe;
+use sha3::{Digest, Sha3_256};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+// Longest history read, each version is a separate network request.
+const MAX_REG_HISTORY: u32 = 1000;
+
+// Network operations the app uses, so commands work the same with Safe and with a mock.
+#[async_trait]
+pub(crate) trait NetworkBackend: Send {
+    // None signs in with a random, empty wallet
+    fn login_with_eth(&mut self, eth_pk: Option<String>) -> Result<(), Error>;
+    fn address(&mut self) -> Result<String, Error>;
+    async fn balance(&mut self) -> Result<(String, String), Error>; // tokens, gas
+    async fn upload(&mut self, data: &[u8]) -> Result<XorName, Error>;
+    async fn download(&mut self, xorname: XorName) -> Result<Vec<u8>, Error>;
+    async fn reg_create(&mut self, data: &[u8], meta: &XorName) -> Result<(), Error>;
+
+    // Reads take the EVM address of the register owner, None for the signed in account.
+    // Only the mock reads other owners: safeapi derives register addresses from the owner's
+    // private key and keeps its network client private, so Safe returns NotSupported.
+    async fn read_reg(&mut self, meta: &XorName, owner: Option<&str>) -> Result<Vec<u8>, Error>;
+    async fn read_reg_version(
+        &mut self,
+        meta: &XorName,
+        version: u32,
+        owner: Option<&str>,
+    ) -> Result<Vec<u8>, Error>;
+    // oldest first, false if cut at MAX_REG_HISTORY
+    async fn reg_history(
+        &mut self,
+        meta: &XorName,
+        owner: Option<&str>,
+    ) -> Result<(Vec<Vec<u8>>, bool), Error>;
+
+    async fn reg_write(&mut self, data: &[u8], meta: &XorName) -> Result<(), Error>;
+    fn log_level(&mut self, level: &str) -> Result<(), Error>;
+}
+
+// Managed state, None when not connected.
+pub(crate) type Client = Mutex<Option<Box<dyn NetworkBackend>>>;
+
+pub(crate) async fn connect(network: &NetworkSetting) -> Result<Box<dyn NetworkBackend>, Error> {
+    match network.to_network() {
+        Some(network) => Ok(Box::new(
+            Safe::connect(network, None, DEFAULT_LOG_LEVEL).await?,
+        )),
+        None => Ok(Box::new(MockBackend::new(shared_mock_network()))),
+    }
+}
+
+// Safe methods are called by full path, so they don't resolve to the trait ones.
+#[async_trait]
+impl NetworkBackend for Safe {
+    fn login_with_eth(&mut self, eth_pk: Option<String>) -> Result<(), Error> {
+        Safe::login_with_eth(self, eth_pk)?;
+        Ok(())
+    }
+
+    fn address(&mut self) -> Result<String, Error> {
+        Ok(Safe::address(self)?.to_string())
+    }
+
+    async fn balance(&mut self) -> Result<(String, String), Error> {
+        let balance = Safe::balance(self).await?;
+        Ok((balance.0.to_string(), balance.1.to_string()))
+    }
+
+    async fn upload(&mut self, data: &[u8]) -> Result<XorName, Error> {
+        match Safe::upload(self, data).await {
+            Ok(xorname) => Ok(xorname),
+            Err(e) => Err(payment_failure(self, e).await),
+        }
+    }
+
+    async fn download(&mut self, xorname: XorName) -> Result<Vec<u8>, Error> {
+        Ok(Safe::download(self, xorname).await?)
+    }
+
+    async fn reg_create(&mut self, data: &[u8], meta: &XorName) -> Result<(), Error> {
+        match Safe::reg_create(self, data, meta).await {
+            Ok(()) => Ok(()),
+            Err(e) => Err(payment_failure(self, e).await),
+        }
+    }
+
+    async fn read_reg(&mut self, meta: &XorName, owner: Option<&str>) -> Result<Vec<u8>, Error> {
+        check_owner(self, owner)?;
+        Ok(Safe::read_reg(self, meta, None).await?)
+    }
+
+    async fn read_reg_version(
+        &mut self,
+        meta: &XorName,
+        version: u32,
+        owner: Option<&str>,
+    ) -> Result<Vec<u8>, Error> {
+        check_owner(self, owner)?;
+        Ok(Safe::read_reg(self, meta, Some(version)).await?)
+    }
+
+    // Safe has no version count, and reading a missing version fails the same as a network
+    // error. So versions are read up to the one equal to the head, any error is returned.
+    // Identical values written in a row end it early, only possible within the same second.
+    async fn reg_history(
+        &mut self,
+        meta: &XorName,
+        owner: Option<&str>,
+    ) -> Result<(Vec<Vec<u8>>, bool), Error> {
+        check_owner(self, owner)?;
+        let head = Safe::read_reg(self, meta, None).await?;
+        let mut history = vec![];
+        for version in 0..MAX_REG_HISTORY {
+            let data = Safe::read_reg(self, meta, Some(version)).await?;
+            let is_head = data == head;
+            history.push(data);
+            if is_head {
+                return Ok((history, true));
+            }
+        }
+        Ok((history, false))
+    }
+
+    async fn reg_write(&mut self, data: &[u8], meta: &XorName) -> Result<(), Error> {
+        match Safe::reg_write(self, data, meta).await {
+            Ok(()) => Ok(()),
+            Err(e) => Err(payment_failure(self, e).await),
+        }
+    }
+
+    fn log_level(&mut self, level: &str) -> Result<(), Error> {
+        Safe::log_level(self, level)?;
+        Ok(())
+    }
+}
+
+// Failed payment is just a message from safeapi, an empty wallet tells what was missing.
+// safeapi doesn't quote the cost, otherwise the message decides.
+async fn payment_failure(safe: &Safe, error: safeapi::Error) -> Error {
+    match Safe::balance(safe).await {
+        Ok((tokens, _)) if tokens.is_zero() => Error::InsufficientFunds,
+        Ok((_, gas)) if gas.is_zero() => Error::InsufficientGas,
+        _ => error.into(),
+    }
+}
+
+// safeapi derives register addresses from the signed in key, and an EVM address is not
+// enough to derive them, so registers of other accounts can't be read with it.
+fn check_owner(safe: &Safe, owner: Option<&str>) -> Result<(), Error> {
+    match owner {
+        Some(owner) if !keys::same_address(owner, &Safe::address(safe)?.to_string()) => Err(
+            Error::NotSupported(format!("Reading registers of {} needs their key.", owner)),
+        ),
+        _ => Ok(()),
+    }
+}
+
+const MOCK_BALANCE: &str = "1000000000000000000"; // 1 token, in atto
+
+// What the mock network stores, shared by all mock clients of the process.
+#[derive(Default)]
+pub(crate) struct MockNetwork {
+    data: HashMap<XorName, Vec<u8>>,
+    registers: HashMap<(String, XorName), Vec<Vec<u8>>>, // by owner address, oldest first
+}
+
+// so data survives disconnecting and reconnecting, like on a real network
+fn shared_mock_network() -> Arc<std::sync::Mutex<MockNetwork>> {
+    static NETWORK: OnceLock<Arc<std::sync::Mutex<MockNetwork>>> = OnceLock::new();
+    NETWORK.get_or_init(Default::default).clone()
+}
+
+// In-memory network for offline development and tests. Data is addressed by its hash.
+pub(crate) struct MockBackend {
+    network: Arc<std::sync::Mutex<MockNetwork>>,
+    eth_pk: Option<String>,
+}
+
+impl MockBackend {
+    pub(crate) fn new(network: Arc<std::sync::Mutex<MockNetwork>>) -> Self {
+        MockBackend {
+            network,
+            eth_pk: None,
+        }
+    }
+
+    fn network(&self) -> std::sync::MutexGuard<'_, MockNetwork> {
+        // nothing can panic while holding the lock, but don't depend on it
+        self.network.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // registers are per account, like on Safe
+    fn register_id(
+        &mut self,
+        meta: &XorName,
+        owner: Option<&str>,
+    ) -> Result<(String, XorName), Error> {
+        let owner = match owner {
+            Some(owner) => owner.to_lowercase(),
+            None => self.address()?,
+        };
+        Ok((owner, *meta))
+    }
+
+    fn versions(&mut self, meta: &XorName, owner: Option<&str>) -> Result<Vec<Vec<u8>>, Error> {
+        let key = self.register_id(meta, owner)?;
+        self.network()
+            .registers
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(String::from("register")))
+    }
+}
+
+#[async_trait]
+impl NetworkBackend for MockBackend {
+    fn login_with_eth(&mut self, eth_pk: Option<String>) -> Result<(), Error> {
+        if let Some(eth_pk) = &eth_pk {
+            keys::eth_address(eth_pk)?; // validate
+        }
+        self.eth_pk = eth_pk;
+        Ok(())
+    }
+
+    fn address(&mut self) -> Result<String, Error> {
+        match &self.eth_pk {
+            Some(eth_pk) => keys::eth_address(eth_pk),
+            None => Ok(format!("0x{}", "0".repeat(40))), // random wallet stand-in
+        }
+    }
+
+    async fn balance(&mut self) -> Result<(String, String), Error> {
+        if self.eth_pk.is_none() {
+            return Err(Error::BadLogin); // like safeapi's NotLoggedIn
+        }
+        Ok((String::from(MOCK_BALANCE), String::from(MOCK_BALANCE)))
+    }
+
+    async fn upload(&mut self, data: &[u8]) -> Result<XorName, Error> {
+        let xorname = XorName(Sha3_256::digest(data).into());
+        self.network().data.insert(xorname, data.to_vec());
+        Ok(xorname)
+    }
+
+    async fn download(&mut self, xorname: XorName) -> Result<Vec<u8>, Error> {
+        self.network()
+            .data
+            .get(&xorname)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("data {}", hex::encode(xorname))))
+    }
+
+    async fn reg_create(&mut self, data: &[u8], meta: &XorName) -> Result<(), Error> {
+        let key = self.register_id(meta, None)?;
+        let mut network = self.network();
+        if network.registers.contains_key(&key) {
+            return Err(Error::Common(String::from("Register already exists.")));
+        }
+        network.registers.insert(key, vec![data.to_vec()]);
+        Ok(())
+    }
+
+    async fn read_reg(&mut self, meta: &XorName, owner: Option<&str>) -> Result<Vec<u8>, Error> {
+        self.versions(meta, owner)?
+            .pop()
+            .ok_or_else(|| Error::NotFound(String::from("register")))
+    }
+
+    async fn read_reg_version(
+        &mut self,
+        meta: &XorName,
+        version: u32,
+        owner: Option<&str>,
+    ) -> Result<Vec<u8>, Error> {
+        self.versions(meta, owner)?
+            .get(version as usize)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("register version {}", version)))
+    }
+
+    async fn reg_history(
+        &mut self,
+        meta: &XorName,
+        owner: Option<&str>,
+    ) -> Result<(Vec<Vec<u8>>, bool), Error> {
+        let mut history = self.versions(meta, owner)?;
+        let complete = history.len() <= MAX_REG_HISTORY as usize;
+        history.truncate(MAX_REG_HISTORY as usize);
+        Ok((history, complete))
+    }
+
+    async fn reg_write(&mut self, data: &[u8], meta: &XorName) -> Result<(), Error> {
+        let key = self.register_id(meta, None)?;
+        self.network()
+            .registers
+            .get_mut(&key)
+            .ok_or_else(|| Error::NotFound(String::from("register")))?
+            .push(data.to_vec());
+        Ok(())
+    }
+
+    fn log_level(&mut self, _level: &str) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::XorNameBuilder;
+
+    fn mock() -> Box<dyn NetworkBackend> {
+        Box::new(MockBackend::new(Default::default()))
+    }
+
+    #[tokio::test]
+    async fn upload_download() {
+        let mut client = mock();
+        let xorname = client.upload(b"song").await.unwrap();
+        assert_eq!(xorname, client.upload(b"song").await.unwrap()); // content addressed
+        assert_ne!(xorname, client.upload(b"other song").await.unwrap());
+
+        assert_eq!(b"song".to_vec(), client.download(xorname).await.unwrap());
+        assert!(matches!(
+            client.download(XorName([7; 32])).await,
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn registers() {
+        let mut client = mock();
+        let meta = XorNameBuilder::from_str("jams")
+            .with_str("playlists")
+            .build();
+
+        assert!(client.read_reg(&meta, None).await.is_err());
+        assert!(client.reg_write(b"[]", &meta).await.is_err());
+
+        client.reg_create(b"[]", &meta).await.unwrap();
+        assert!(client.reg_create(b"[]", &meta).await.is_err());
+        assert_eq!(b"[]".to_vec(), client.read_reg(&meta, None).await.unwrap());
+
+        client.reg_write(b"[1]", &meta).await.unwrap();
+        assert_eq!(b"[1]".to_vec(), client.read_reg(&meta, None).await.unwrap());
+
+        assert_eq!(
+            b"[]".to_vec(),
+            client.read_reg_version(&meta, 0, None).await.unwrap()
+        );
+        assert!(client.read_reg_version(&meta, 2, None).await.is_err());
+        assert_eq!(
+            (vec![b"[]".to_vec(), b"[1]".to_vec()], true),
+            client.reg_history(&meta, None).await.unwrap()
+        );
+
+        for i in 0..MAX_REG_HISTORY {
+            client
+                .reg_write(format!("[{}]", i).as_bytes(), &meta)
+                .await
+                .unwrap();
+        }
+        let (history, complete) = client.reg_history(&meta, None).await.unwrap();
+        assert_eq!((MAX_REG_HISTORY as usize, false), (history.len(), complete));
+        assert_eq!(b"[]".to_vec(), history[0]); // oldest kept
+    }
+
+    #[tokio::test]
+    async fn shared_between_clients() {
+        let network = Arc::new(std::sync::Mutex::new(MockNetwork::default()));
+        let mut first = MockBackend::new(network.clone());
+        let mut second = MockBackend::new(network);
+
+        let xorname = first.upload(b"song").await.unwrap();
+        assert_eq!(b"song".to_vec(), second.download(xorname).await.unwrap());
+
+        // registers are per account, others read them by owner
+        let pk = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let owner = "0xF39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+        let meta = XorNameBuilder::from_str("jams").with_str("library").build();
+        first.login_with_eth(Some(pk.into())).unwrap();
+        first.reg_create(b"[]", &meta).await.unwrap();
+        assert!(second.read_reg(&meta, None).await.is_err());
+        assert_eq!(
+            b"[]".to_vec(),
+            second.read_reg(&meta, Some(owner)).await.unwrap()
+        );
+        assert!(second.reg_write(b"[1]", &meta).await.is_err());
+    }
+
+    #[test]
+    fn login() {
+        let mut client = mock();
+        let pk = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+        client.login_with_eth(Some(pk.into())).unwrap();
+        assert_eq!(
+            "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            client.address().unwrap()
+        );
+        assert!(client.login_with_eth(Some("not a key".into())).is_err());
+
+        client.login_with_eth(None).unwrap();
+        assert_ne!(keys::eth_address(pk).unwrap(), client.address().unwrap());
+    }
+}
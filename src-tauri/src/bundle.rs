@@ -0,0 +1,109 @@
+use crate::profile::AccountProfile;
+use crate::{Deserialize, Error, Serialize};
+
+// Portable account file, used to move an account between machines.
+// Key stays encrypted with the account password, exactly as in SK_FILENAME.
+
+const BUNDLE_FORMAT: &str = "jams-account";
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AccountBundle {
+    format: String,
+    version: u32,
+    pub(crate) username: String,
+    pub(crate) address: String,
+    pub(crate) created_at: u64, // of the account, seconds since UNIX epoch
+    encrypted_key: String,      // hex of Safe::encrypt_eth output
+    #[serde(default)]
+    pub(crate) profile: Option<AccountProfile>, // None in bundles of older versions
+}
+
+impl AccountBundle {
+    pub(crate) fn new(
+        username: String,
+        address: String,
+        encrypted_key: &[u8],
+        profile: AccountProfile,
+    ) -> Self {
+        AccountBundle {
+            format: String::from(BUNDLE_FORMAT),
+            version: BUNDLE_VERSION,
+            username,
+            address,
+            created_at: profile.created_at,
+            encrypted_key: hex::encode(encrypted_key),
+            profile: Some(profile),
+        }
+    }
+
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::Common(format!("Could not serialize account bundle: {}", e)))
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let bundle: AccountBundle = serde_json::from_slice(bytes)
+            .map_err(|e| Error::Common(format!("Not an account bundle: {}", e)))?;
+
+        if bundle.format != BUNDLE_FORMAT {
+            return Err(Error::Common(format!(
+                "Unknown bundle format: {}",
+                bundle.format
+            )));
+        }
+        if bundle.version > BUNDLE_VERSION {
+            return Err(Error::Common(format!(
+                "Account bundle version {} is newer than supported {}. Please update the app.",
+                bundle.version, BUNDLE_VERSION
+            )));
+        }
+
+        Ok(bundle)
+    }
+
+    pub(crate) fn encrypted_key(&self) -> Result<Vec<u8>, Error> {
+        hex::decode(&self.encrypted_key)
+            .map_err(|e| Error::Common(format!("Corrupted key in account bundle: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundle_roundtrip() {
+        let mut profile = AccountProfile::new("Alice".into());
+        profile.created_at = 1_600_000_000;
+        let bundle =
+            AccountBundle::new("alice".into(), "0x1234".into(), &[1, 2, 3], profile.clone());
+        let parsed = AccountBundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(bundle, parsed);
+        assert_eq!(vec![1, 2, 3], parsed.encrypted_key().unwrap());
+        assert_eq!(1_600_000_000, parsed.created_at);
+        assert_eq!(Some(profile), parsed.profile);
+
+        // older bundle, without a profile
+        let mut json: serde_json::Value =
+            serde_json::from_slice(&bundle.to_bytes().unwrap()).unwrap();
+        json.as_object_mut().unwrap().remove("profile");
+        let parsed = AccountBundle::from_bytes(json.to_string().as_bytes()).unwrap();
+        assert_eq!(None, parsed.profile);
+    }
+
+    #[test]
+    fn bundle_newer_version() {
+        let profile = AccountProfile::new("alice".into());
+        let mut bundle = AccountBundle::new("alice".into(), "0x1234".into(), &[1], profile);
+        bundle.version = BUNDLE_VERSION + 1;
+        assert!(AccountBundle::from_bytes(&bundle.to_bytes().unwrap()).is_err());
+    }
+
+    #[test]
+    fn bundle_wrong_format() {
+        assert!(AccountBundle::from_bytes(b"{\"format\":\"other\"}").is_err());
+        assert!(AccountBundle::from_bytes(b"garbage").is_err());
+    }
+}
@@ -0,0 +1,420 @@
+use crate::backend::{self, Client, NetworkBackend};
+use crate::diagnostics::{self, Operation};
+use crate::network::NetworkSetting;
+use crate::operations;
+use crate::queue;
+use crate::wallet::WalletState;
+use crate::{Error, Serialize};
+use futures::future::{AbortHandle, AbortRegistration, Abortable, Aborted};
+use futures::lock::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+use tracing::{debug, error, info, warn};
+
+pub(crate) const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_secs(2);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub(crate) enum ConnectionState {
+    Disconnected,
+    Connecting,
+    Connected,
+    Failed { reason: String },
+    Reconnecting,
+    Disconnecting, // waiting for operations in progress
+}
+
+// Network connection bookkeeping, the client itself lives in Client.
+pub(crate) struct Connection {
+    state: ConnectionState,
+    pub(crate) network: NetworkSetting, // used by next connect, and for reconnecting
+    pub(crate) connect_timeout: Duration,
+    abort: Option<AbortHandle>, // cancels the connecting attempt in progress
+    connected_at: Option<Instant>,
+}
+
+impl Connection {
+    pub(crate) fn new(network: NetworkSetting) -> Self {
+        Connection {
+            state: ConnectionState::Disconnected,
+            network,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            abort: None,
+            connected_at: None,
+        }
+    }
+
+    pub(crate) fn state(&self) -> &ConnectionState {
+        &self.state
+    }
+
+    // every transition is announced to the frontend
+    pub(crate) fn set_state(&mut self, state: ConnectionState, app: &AppHandle) {
+        if self.state == state {
+            return;
+        }
+        info!(from = ?self.state, to = ?state, "Connection state changed.");
+        self.state = state;
+        self.abort = None;
+        self.connected_at = (self.state == ConnectionState::Connected).then(Instant::now);
+
+        let _ = app
+            .emit("connection_state_changed", &self.state)
+            .inspect_err(|e| error!("{}", e));
+    }
+
+    // None when not connected
+    pub(crate) fn uptime(&self) -> Option<Duration> {
+        self.connected_at.map(|connected_at| connected_at.elapsed())
+    }
+
+    // registration for the next connecting attempt, so it can be cancelled
+    fn arm(&mut self) -> AbortRegistration {
+        let (handle, registration) = AbortHandle::new_pair();
+        self.abort = Some(handle);
+        registration
+    }
+
+    fn is_connecting(&self) -> bool {
+        matches!(
+            self.state,
+            ConnectionState::Connecting | ConnectionState::Reconnecting
+        )
+    }
+}
+
+// Wakes up the supervisor before HEALTH_CHECK_INTERVAL passes.
+#[derive(Default)]
+pub(crate) struct HealthCheck(Notify);
+
+pub(crate) async fn set_state(app: &AppHandle, state: ConnectionState) {
+    app.state::<Mutex<Connection>>()
+        .lock()
+        .await
+        .set_state(state, app);
+}
+
+pub(crate) async fn state(app: &AppHandle) -> ConnectionState {
+    app.state::<Mutex<Connection>>().lock().await.state().clone()
+}
+
+// Returns None if already connected to the network, so there is nothing to do.
+async fn start_connecting(
+    app: &AppHandle,
+    network: Option<NetworkSetting>,
+) -> Result<Option<(NetworkSetting, AbortRegistration, Duration)>, Error> {
+    let connection = app.state::<Mutex<Connection>>();
+    let mut connection = connection.lock().await;
+
+    match connection.state() {
+        ConnectionState::Connected => match network {
+            Some(network) if network != connection.network => Err(Error::Common(String::from(
+                "Already connected to another network, disconnect first.",
+            ))),
+            _ => Ok(None),
+        },
+        ConnectionState::Connecting | ConnectionState::Reconnecting => {
+            Err(Error::AlreadyConnecting)
+        }
+        ConnectionState::Disconnecting => Err(Error::Disconnecting),
+        ConnectionState::Disconnected | ConnectionState::Failed { .. } => {
+            if let Some(network) = network {
+                connection.network = network;
+            }
+            connection.set_state(ConnectionState::Connecting, app);
+            Ok(Some((
+                connection.network.clone(),
+                connection.arm(),
+                connection.connect_timeout,
+            )))
+        }
+    }
+}
+
+// backend::connect, bounded by timeout and abortable through the registration.
+async fn attempt(
+    network: &NetworkSetting,
+    registration: AbortRegistration,
+    timeout: Duration,
+) -> Result<Box<dyn NetworkBackend>, Error> {
+    let connecting = Abortable::new(backend::connect(network), registration);
+
+    match tokio::time::timeout(timeout, connecting).await {
+        Err(_) => Err(Error::ConnectTimeout(timeout.as_secs())),
+        Ok(Err(Aborted)) => Err(Error::ConnectCancelled),
+        Ok(Ok(result)) => result,
+    }
+}
+
+// Ends connecting or reconnecting with Failed state, does nothing when it already ended.
+async fn fail(app: &AppHandle, reason: String) {
+    let was_reconnecting = {
+        let connection = app.state::<Mutex<Connection>>();
+        let mut connection = connection.lock().await;
+        if !connection.is_connecting() {
+            return;
+        }
+        let was_reconnecting = connection.state == ConnectionState::Reconnecting;
+
+        if let Some(abort) = connection.abort.take() {
+            abort.abort();
+        }
+        connection.set_state(ConnectionState::Failed { reason: reason.clone() }, app);
+        was_reconnecting
+    };
+
+    // client of the lost connection is of no use anymore
+    if was_reconnecting {
+        app.state::<Client>().lock().await.take();
+    }
+
+    warn!(reason = %reason, "Connecting failed.");
+    let _ = app
+        .emit("connect_failed", &reason)
+        .inspect_err(|e| error!("{}", e));
+}
+
+// Connects to the given network, or the last used one if None. False when already connected to it.
+pub(crate) async fn connect(
+    app: &AppHandle,
+    network: Option<NetworkSetting>,
+) -> Result<bool, Error> {
+    let Some((network, registration, timeout)) = start_connecting(app, network).await? else {
+        debug!("Already connected.");
+        return Ok(false);
+    };
+
+    info!(?network, "Connecting...");
+
+    let start = Instant::now();
+    let result = attempt(&network, registration, timeout).await;
+    diagnostics::record(app, Operation::Connect, start.elapsed(), result.as_ref().err()).await;
+
+    let client = match result {
+        Ok(client) => client,
+        Err(e) => {
+            fail(app, format!("{}", e)).await;
+            return Err(e);
+        }
+    };
+
+    let connection = app.state::<Mutex<Connection>>();
+    let mut connection = connection.lock().await;
+    // cancelled right after connecting
+    if connection.state != ConnectionState::Connecting {
+        return Err(Error::ConnectCancelled);
+    }
+
+    info!("Connected.");
+
+    // Store the client in the application's state
+    *(app.state::<Client>().lock().await) = Some(client);
+    connection.set_state(ConnectionState::Connected, app);
+
+    queue::schedule_replay(app);
+
+    Ok(true)
+}
+
+// Stops connecting or reconnecting, the attempt in progress gets aborted.
+pub(crate) async fn cancel(app: &AppHandle) -> Result<(), Error> {
+    if !app.state::<Mutex<Connection>>().lock().await.is_connecting() {
+        return Err(Error::Common(String::from("Not connecting.")));
+    }
+
+    fail(app, format!("{}", Error::ConnectCancelled)).await;
+    Ok(())
+}
+
+// Drains operations in progress and drops the client. Returns aborted operations.
+pub(crate) async fn disconnect(app: &AppHandle, timeout: Duration) -> Result<Vec<Operation>, Error> {
+    {
+        let connection = app.state::<Mutex<Connection>>();
+        let mut connection = connection.lock().await;
+        match connection.state {
+            ConnectionState::Connected | ConnectionState::Reconnecting => {}
+            ConnectionState::Disconnecting => return Err(Error::Disconnecting),
+            _ => return Err(Error::NotConnected),
+        }
+        if let Some(abort) = connection.abort.take() {
+            abort.abort(); // reconnect attempt
+        }
+        connection.set_state(ConnectionState::Disconnecting, app);
+    }
+
+    let aborted = operations::drain(app, timeout).await;
+    app.state::<Client>().lock().await.take();
+    operations::reopen(app).await;
+    set_state(app, ConnectionState::Disconnected).await;
+
+    Ok(aborted)
+}
+
+// To be called when a network operation fails, the connection gets checked right away.
+pub(crate) fn operation_failed(app: &AppHandle) {
+    app.state::<HealthCheck>().0.notify_one();
+}
+
+fn backoff_delay(attempt: u32) -> Duration {
+    RECONNECT_INITIAL_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(RECONNECT_MAX_DELAY)
+}
+
+// Safe has no call probing Autonomi peers, and a missing record reads the same as a network
+// failure. So balance is the probe: it reaches only the EVM RPC, and catches a lost internet
+// connection, but not an outage of Autonomi alone. Failed operations trigger a check anyway.
+// None when nobody is signed in with a key, balance then fails without reaching the network.
+async fn probe(client: &mut dyn NetworkBackend) -> Option<Result<(), Error>> {
+    match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, client.balance()).await {
+        Ok(Err(Error::BadLogin)) => None,
+        Ok(result) => Some(result.map(|_| ())),
+        Err(_) => Some(Err(Error::Timeout(String::from("health check")))),
+    }
+}
+
+async fn is_healthy(app: &AppHandle) -> bool {
+    let start = Instant::now();
+    let result = {
+        let client = app.state::<Client>();
+        let Ok(mut client) = tokio::time::timeout(HEALTH_CHECK_TIMEOUT, client.lock()).await else {
+            debug!("Health check skipped, client is busy.");
+            return true; // failure of the operation using it triggers another check
+        };
+        let Some(client) = client.as_mut() else {
+            return false;
+        };
+        match probe(client.as_mut()).await {
+            Some(result) => result,
+            None => {
+                debug!("Health check skipped, nobody is signed in.");
+                return true;
+            }
+        }
+    };
+
+    if let Err(e) = &result {
+        warn!("Health check failed: {}", e);
+    }
+    diagnostics::record(app, Operation::Balance, start.elapsed(), result.as_ref().err()).await;
+    result.is_ok()
+}
+
+// Connects again with the last used network and signs the user in, until it succeeds
+// or the user disconnects.
+async fn reconnect(app: &AppHandle) {
+    let network = {
+        let connection = app.state::<Mutex<Connection>>();
+        let mut connection = connection.lock().await;
+        if connection.state != ConnectionState::Connected {
+            return;
+        }
+        connection.set_state(ConnectionState::Reconnecting, app);
+        connection.network.clone()
+    };
+
+    let mut attempts = 0;
+    loop {
+        // user could have disconnected or cancelled meanwhile
+        let (registration, timeout) = {
+            let connection = app.state::<Mutex<Connection>>();
+            let mut connection = connection.lock().await;
+            if connection.state != ConnectionState::Reconnecting {
+                return;
+            }
+            (connection.arm(), connection.connect_timeout)
+        };
+
+        attempts += 1;
+        let _ = app
+            .emit("reconnecting", attempts)
+            .inspect_err(|e| error!("{}", e));
+
+        let start = Instant::now();
+        let result = attempt(&network, registration, timeout).await;
+        diagnostics::record(app, Operation::Connect, start.elapsed(), result.as_ref().err()).await;
+
+        if state(app).await != ConnectionState::Reconnecting {
+            return;
+        }
+
+        match result {
+            Ok(mut client) => {
+                let eth_pk = app.state::<Mutex<WalletState>>().lock().await.eth_pk();
+                if let Some(eth_pk) = eth_pk {
+                    if let Err(e) = client.login_with_eth(Some(eth_pk)) {
+                        error!("Could not sign in after reconnecting: {}", e);
+                    }
+                }
+
+                let connection = app.state::<Mutex<Connection>>();
+                let mut connection = connection.lock().await;
+                if connection.state != ConnectionState::Reconnecting {
+                    return;
+                }
+                *(app.state::<Client>().lock().await) = Some(client);
+                connection.set_state(ConnectionState::Connected, app);
+
+                info!(attempts, "Reconnected.");
+                queue::schedule_replay(app);
+                let _ = app
+                    .emit("reconnected", ())
+                    .inspect_err(|e| error!("{}", e));
+                return;
+            }
+            Err(e) => {
+                let delay = backoff_delay(attempts);
+                warn!(attempt = attempts, next_in = ?delay, "Reconnect failed: {}", e);
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
+
+// Background task checking the connection, and reconnecting when it is lost.
+pub(crate) async fn supervise(app: AppHandle) {
+    let health_check = app.state::<HealthCheck>();
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(HEALTH_CHECK_INTERVAL) => {}
+            _ = health_check.0.notified() => {}
+        }
+
+        if state(&app).await != ConnectionState::Connected {
+            continue;
+        }
+        if !is_healthy(&app).await {
+            reconnect(&app).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // right after connecting nobody is signed in, that is no reason to reconnect
+    #[tokio::test]
+    async fn probe_without_login() {
+        let mut client = backend::MockBackend::new(Default::default());
+        assert!(probe(&mut client).await.is_none());
+
+        let pk = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        client.login_with_eth(Some(pk.into())).unwrap();
+        assert!(matches!(probe(&mut client).await, Some(Ok(()))));
+    }
+
+    #[test]
+    fn backoff() {
+        assert_eq!(RECONNECT_INITIAL_DELAY, backoff_delay(1));
+        assert_eq!(RECONNECT_INITIAL_DELAY * 2, backoff_delay(2));
+        assert_eq!(RECONNECT_INITIAL_DELAY * 8, backoff_delay(4));
+        assert_eq!(RECONNECT_MAX_DELAY, backoff_delay(20));
+        assert_eq!(RECONNECT_MAX_DELAY, backoff_delay(u32::MAX));
+    }
+}
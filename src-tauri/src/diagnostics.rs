@@ -0,0 +1,157 @@
+use crate::connection::{Connection, ConnectionState};
+use crate::network::NetworkSetting;
+use crate::{profile, Error, Serialize};
+use futures::lock::Mutex;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const RECENT_FAILURES_WINDOW: u64 = 60 * 60; // seconds
+const MAX_RECENT_FAILURES: usize = 1000;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Operation {
+    Connect,
+    Upload,
+    Download,
+    RegCreate,
+    ReadReg,
+    RegWrite,
+    Balance,
+}
+
+// error code counts by operation
+pub(crate) type FailureCounts = BTreeMap<Operation, BTreeMap<&'static str, usize>>;
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OperationRecord {
+    pub(crate) operation: Operation,
+    pub(crate) at: u64, // seconds since UNIX epoch
+    pub(crate) latency_ms: u64,
+}
+
+// Outcomes of network operations, for the diagnostics report.
+#[derive(Default)]
+pub(crate) struct Diagnostics {
+    last_success: Option<OperationRecord>,
+    failures: VecDeque<(u64, Operation, &'static str)>, // timestamp, error code, oldest first
+}
+
+impl Diagnostics {
+    pub(crate) fn succeeded(&mut self, operation: Operation, at: u64, latency: Duration) {
+        self.last_success = Some(OperationRecord {
+            operation,
+            at,
+            latency_ms: latency.as_millis() as u64,
+        });
+    }
+
+    pub(crate) fn failed(&mut self, operation: Operation, code: &'static str, at: u64) {
+        self.failures.push_back((at, operation, code));
+        if self.failures.len() > MAX_RECENT_FAILURES {
+            self.failures.pop_front();
+        }
+    }
+
+    // failures within RECENT_FAILURES_WINDOW before now
+    pub(crate) fn recent_failures(&self, now: u64) -> FailureCounts {
+        let since = now.saturating_sub(RECENT_FAILURES_WINDOW);
+        let mut counts = FailureCounts::new();
+        for (_, operation, code) in self.failures.iter().filter(|(at, _, _)| *at >= since) {
+            *counts.entry(*operation).or_default().entry(code).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NetworkDiagnostics {
+    pub(crate) network: NetworkSetting,
+    pub(crate) state: ConnectionState,
+    pub(crate) uptime_secs: Option<u64>, // None when not connected
+    pub(crate) bootstrap_peers: Option<usize>, // None when the client picks them itself
+    pub(crate) last_success: Option<OperationRecord>,
+    pub(crate) recent_failures: FailureCounts, // within the last hour
+}
+
+// error is None on success
+pub(crate) async fn record(
+    app: &AppHandle,
+    operation: Operation,
+    latency: Duration,
+    error: Option<&Error>,
+) {
+    let diagnostics = app.state::<Mutex<Diagnostics>>();
+    let mut diagnostics = diagnostics.lock().await;
+    match error {
+        None => diagnostics.succeeded(operation, profile::now(), latency),
+        Some(e) => diagnostics.failed(operation, e.code(), profile::now()),
+    }
+}
+
+pub(crate) async fn collect(app: &AppHandle) -> NetworkDiagnostics {
+    let (network, state, uptime_secs) = {
+        let connection = app.state::<Mutex<Connection>>();
+        let connection = connection.lock().await;
+        (
+            connection.network.clone(),
+            connection.state().clone(),
+            connection.uptime().map(|uptime| uptime.as_secs()),
+        )
+    };
+
+    // the client does not expose its routing table
+    let bootstrap_peers = match &network {
+        NetworkSetting::Custom { peers } => Some(peers.len()),
+        NetworkSetting::Mock => Some(0),
+        NetworkSetting::Mainnet | NetworkSetting::Alpha => None,
+    };
+
+    let diagnostics = app.state::<Mutex<Diagnostics>>();
+    let diagnostics = diagnostics.lock().await;
+    NetworkDiagnostics {
+        network,
+        state,
+        uptime_secs,
+        bootstrap_peers,
+        last_success: diagnostics.last_success.clone(),
+        recent_failures: diagnostics.recent_failures(profile::now()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failure_counts() {
+        let mut diagnostics = Diagnostics::default();
+        let now = 1_000_000;
+
+        diagnostics.failed(Operation::Upload, "Network", now - RECENT_FAILURES_WINDOW - 1); // too old
+        diagnostics.failed(Operation::Upload, "Network", now - 10);
+        diagnostics.failed(Operation::Upload, "InsufficientFunds", now);
+        diagnostics.failed(Operation::Upload, "Network", now);
+        diagnostics.failed(Operation::ReadReg, "Timeout", now);
+        diagnostics.succeeded(Operation::Download, now, Duration::from_millis(1500));
+
+        let counts = diagnostics.recent_failures(now);
+        assert_eq!(Some(&2), counts[&Operation::Upload].get("Network"));
+        assert_eq!(Some(&1), counts[&Operation::Upload].get("InsufficientFunds"));
+        assert_eq!(Some(&1), counts[&Operation::ReadReg].get("Timeout"));
+        assert_eq!(None, counts.get(&Operation::Download));
+
+        let last = diagnostics.last_success.clone().unwrap();
+        assert_eq!(Operation::Download, last.operation);
+        assert_eq!(1500, last.latency_ms);
+
+        for _ in 0..MAX_RECENT_FAILURES {
+            diagnostics.failed(Operation::Balance, "Network", now);
+        }
+        assert_eq!(MAX_RECENT_FAILURES, diagnostics.failures.len());
+        assert_eq!(None, diagnostics.recent_failures(now).get(&Operation::Upload));
+    }
+}
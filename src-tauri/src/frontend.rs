@@ -1,4 +1,7 @@
 use crate::{Deserialize, Serialize, Error, XorName, PathBuf};
+use crate::account_name::AccountName;
+use crate::profile::AccountProfile;
+use crate::diagnostics::Operation;
 
 
 // stuff that has to be in sync with frontend code.
@@ -11,6 +14,22 @@ pub(crate) struct SimpleAccountUser {
     pub(crate) address: String,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AccountSummary {
+    pub(crate) username: AccountName,
+    pub(crate) address: Option<String>,
+    pub(crate) watch_only: bool,
+    #[serde(flatten)]
+    pub(crate) profile: AccountProfile,
+}
+
+// payload of "disconnected" event
+#[derive(Serialize, Clone)]
+pub(crate) struct Disconnected {
+    pub(crate) aborted: Vec<Operation>, // operations that did not finish in time
+}
+
 #[derive(Default, Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct FileMetadata {
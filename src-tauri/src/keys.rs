@@ -0,0 +1,148 @@
+use crate::{Deserialize, Error, Serialize};
+use bip32::{DerivationPath, XPrv};
+use bip39::{Language, Mnemonic};
+use k256::ecdsa::SigningKey;
+use sha3::{Digest, Keccak256, Sha3_256};
+
+// BIP44 path of the first Ethereum account, same as most wallets use.
+const ETH_DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
+const REGISTER_KEY_CONTEXT: &[u8] = b"jams private register key v1";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) enum MnemonicError {
+    BadWordCount(usize),
+    UnknownWord(String),
+    BadChecksum,
+}
+
+// Computes EVM address of an ethereum private key (hex, with or without 0x prefix).
+pub(crate) fn eth_address(eth_pk: &str) -> Result<String, Error> {
+    let bytes = hex::decode(eth_pk.trim_start_matches("0x"))
+        .map_err(|e| Error::Common(format!("Invalid private key: {}", e)))?;
+    let key = SigningKey::from_slice(&bytes)
+        .map_err(|e| Error::Common(format!("Invalid private key: {}", e)))?;
+
+    let public = key.verifying_key().to_encoded_point(false);
+    let hash = Keccak256::digest(&public.as_bytes()[1..]); // skip 0x04 prefix
+    Ok(format!("0x{}", hex::encode(&hash[12..])))
+}
+
+pub(crate) fn generate_mnemonic(words: usize) -> Result<Mnemonic, Error> {
+    if words != 12 && words != 24 {
+        return Err(Error::BadMnemonic(MnemonicError::BadWordCount(words)));
+    }
+    Mnemonic::generate_in(Language::English, words)
+        .map_err(|e| Error::Common(format!("Could not generate mnemonic: {}", e)))
+}
+
+// Accepts any whitespace and letter case, as users tend to copy phrases from various places.
+pub(crate) fn parse_mnemonic(phrase: &str) -> Result<Mnemonic, MnemonicError> {
+    let words = phrase
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<String>>();
+
+    Mnemonic::parse_in_normalized(Language::English, &words.join(" ")).map_err(|e| match e {
+        bip39::Error::UnknownWord(i) => MnemonicError::UnknownWord(words[i].clone()),
+        bip39::Error::InvalidChecksum => MnemonicError::BadChecksum,
+        _ => MnemonicError::BadWordCount(words.len()),
+    })
+}
+
+// returns hex-encoded ethereum private key
+pub(crate) fn mnemonic_to_eth_pk(mnemonic: &Mnemonic) -> Result<String, Error> {
+    let seed = mnemonic.to_seed("");
+    let path: DerivationPath = ETH_DERIVATION_PATH
+        .parse()
+        .expect("Derivation path constant should be valid.");
+    let xprv = XPrv::derive_from_path(seed, &path)
+        .map_err(|e| Error::Common(format!("Could not derive key from mnemonic: {}", e)))?;
+
+    Ok(hex::encode(xprv.private_key().to_bytes()))
+}
+
+// 0x followed by 40 hex digits, checksum is not verified
+pub(crate) fn is_eth_address(address: &str) -> bool {
+    address.len() == 42
+        && address.starts_with("0x")
+        && address[2..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// EVM addresses can be checksummed (mixed case) or not.
+pub(crate) fn same_address(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+// Symmetric key for private registers. Derived, so it is recovered along with the account key.
+pub(crate) fn register_key(eth_pk: &str) -> Result<[u8; 32], Error> {
+    let bytes = hex::decode(eth_pk.trim_start_matches("0x"))
+        .map_err(|e| Error::Common(format!("Invalid private key: {}", e)))?;
+    Ok(Sha3_256::new()
+        .chain_update(REGISTER_KEY_CONTEXT)
+        .chain_update(bytes)
+        .finalize()
+        .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_from_key() {
+        // well-known hardhat/anvil account #0
+        let pk = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let address = eth_address(pk).unwrap();
+        assert!(same_address(&address, "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"));
+        assert_eq!(address, eth_address(&format!("0x{}", pk)).unwrap());
+    }
+
+    #[test]
+    fn address_format() {
+        assert!(is_eth_address("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"));
+        assert!(!is_eth_address("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266"));
+        assert!(!is_eth_address("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb9226"));
+        assert!(!is_eth_address("0xg39Fd6e51aad88F6F4ce6aB8827279cffFb92266"));
+    }
+
+    #[test]
+    fn key_from_mnemonic() {
+        // hardhat/anvil default mnemonic, derives account #0
+        let mnemonic = parse_mnemonic("test test test test test test test test test test test junk").unwrap();
+        assert_eq!(
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            mnemonic_to_eth_pk(&mnemonic).unwrap()
+        );
+    }
+
+    #[test]
+    fn mnemonic_loose_formatting() {
+        assert!(parse_mnemonic("  Test test\ttest test test test test test test test test   JUNK\n").is_ok());
+    }
+
+    #[test]
+    fn mnemonic_errors() {
+        assert_eq!(
+            MnemonicError::BadChecksum,
+            parse_mnemonic("test test test test test test test test test test test test").unwrap_err()
+        );
+        assert_eq!(
+            MnemonicError::UnknownWord(String::from("jamz")),
+            parse_mnemonic("test test test test test test test test test test test jamz").unwrap_err()
+        );
+        assert_eq!(
+            MnemonicError::BadWordCount(3),
+            parse_mnemonic("test test junk").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn generated_mnemonic_parses() {
+        for words in [12, 24] {
+            let mnemonic = generate_mnemonic(words).unwrap();
+            assert_eq!(words, mnemonic.word_count());
+            assert_eq!(mnemonic, parse_mnemonic(&mnemonic.to_string()).unwrap());
+        }
+        assert!(generate_mnemonic(13).is_err());
+    }
+}
@@ -0,0 +1,597 @@
+use crate::account_name::AccountName;
+use crate::{Error, PathBuf, Serialize};
+use aes_gcm::aead::{rand_core::RngCore, OsRng};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use tracing::{error, info, warn};
+
+// Persistence of encrypted account keys. Implementations only move bytes around,
+// encryption and password checks are done by callers.
+pub(crate) trait KeyStore: Send + Sync {
+    // fails if the account already exists
+    fn create(&self, login: &AccountName, key: &[u8]) -> Result<(), Error>;
+
+    // None if there is no such account
+    fn load(&self, login: &AccountName) -> Result<Option<Vec<u8>>, Error>;
+
+    // overwrites key of an existing account, e.g. after password change
+    fn replace(&self, login: &AccountName, key: &[u8]) -> Result<(), Error>;
+
+    // accounts with a stored key, the same that exists() is true for
+    fn list(&self) -> Result<Vec<AccountName>, Error>;
+
+    // removing non-existent account is not an error
+    fn delete(&self, login: &AccountName) -> Result<(), Error>;
+
+    fn rename(&self, from: &AccountName, to: &AccountName) -> Result<(), Error>;
+
+    // Moves account away instead of deleting it, so it can be restored later.
+    fn trash(&self, login: &AccountName) -> Result<TrashEntry, Error>;
+
+    fn list_trash(&self) -> Result<Vec<TrashEntry>, Error>;
+
+    // restores under a given name, which may differ from the original one
+    fn restore(&self, id: &str, login: &AccountName) -> Result<(), Error>;
+
+    // permanently removes accounts trashed before given time (seconds since UNIX epoch)
+    fn purge_trash(&self, before: u64) -> Result<(), Error>;
+
+    fn exists(&self, login: &AccountName) -> Result<bool, Error> {
+        Ok(self.load(login)?.is_some())
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TrashEntry {
+    pub(crate) id: String,
+    pub(crate) login: AccountName,
+    pub(crate) deleted_at: u64, // seconds since UNIX epoch
+}
+
+impl TrashEntry {
+    fn new(login: &AccountName) -> Self {
+        let deleted_at = crate::profile::now();
+        TrashEntry {
+            // random part, the same name can be deleted again within a second
+            id: format!("{}.{:08x}_{}", deleted_at, OsRng.next_u32(), login),
+            login: login.clone(),
+            deleted_at,
+        }
+    }
+
+    // id is "<deleted_at>.<random>_<login>", or "<deleted_at>_<login>" by older versions
+    fn from_id(id: &str) -> Option<Self> {
+        let (stamp, login) = id.split_once('_')?;
+        let deleted_at = stamp.split('.').next()?;
+        Some(TrashEntry {
+            id: String::from(id),
+            login: AccountName::parse(login).ok()?,
+            deleted_at: deleted_at.parse().ok()?,
+        })
+    }
+}
+
+// Default layout: <app data>/accounts/<login>/sk.key
+// and deleted accounts in <app data>/trash/<deleted_at>.<random>_<login>/sk.key
+pub(crate) struct FileKeyStore {
+    accounts_dir: PathBuf,
+    trash_dir: PathBuf,
+}
+
+impl FileKeyStore {
+    pub(crate) fn new(accounts_dir: PathBuf, trash_dir: PathBuf) -> Self {
+        FileKeyStore {
+            accounts_dir,
+            trash_dir,
+        }
+    }
+
+    pub(crate) fn account_dir(&self, login: &AccountName) -> PathBuf {
+        self.accounts_dir.join(login)
+    }
+
+    fn key_file(&self, login: &AccountName) -> PathBuf {
+        self.account_dir(login).join(crate::SK_FILENAME)
+    }
+
+    fn account_dirs(&self) -> Result<Vec<(String, PathBuf)>, Error> {
+        subdirs(&self.accounts_dir)
+    }
+
+    // Renames directories of accounts created before names were validated.
+    // Does nothing for already valid names, so it is safe to run on every start.
+    // An account that can't be renamed is left as it is, the others still get migrated.
+    pub(crate) fn migrate_legacy_names(&self) -> Result<(), Error> {
+        for (raw, path) in self.account_dirs()? {
+            let target = match AccountName::parse(&raw) {
+                Ok(name) if name.as_str() == raw => continue,
+                Ok(name) => {
+                    // only normalization differs, some filesystems do not preserve it anyway
+                    if self.account_dir(&name).exists() {
+                        continue;
+                    }
+                    name
+                }
+                Err(_) => {
+                    let base = AccountName::sanitize(&raw);
+                    let mut name = base.clone();
+                    let mut n = 2;
+                    while self.account_dir(&name).exists() {
+                        name = base.with_suffix(n);
+                        n += 1;
+                    }
+                    name
+                }
+            };
+
+            let target_dir = self.account_dir(&target);
+            match fs::rename(&path, &target_dir) {
+                Ok(()) => info!(from = ?raw, to = target.as_str(), "Account renamed."),
+                Err(e) => error!(
+                    "Could not migrate account {} to {}: {}",
+                    path.display(),
+                    target_dir.display(),
+                    e
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl KeyStore for FileKeyStore {
+    fn create(&self, login: &AccountName, key: &[u8]) -> Result<(), Error> {
+        let sk_dir = self.account_dir(login);
+        let sk_file = self.key_file(login);
+
+        // directory without a key is a watch-only account
+        if self.exists(login)? || sk_dir.exists() {
+            return Err(Error::AccountExists(login.to_string()));
+        }
+
+        fs::create_dir_all(&sk_dir).map_err(|_| {
+            Error::Common(format!("Could not create user dir: {}", &sk_dir.display()))
+        })?;
+        fs::write(&sk_file, key).map_err(|_| {
+            Error::Common(format!(
+                "Could not save user key file: {}",
+                &sk_file.display()
+            ))
+        })
+    }
+
+    fn load(&self, login: &AccountName) -> Result<Option<Vec<u8>>, Error> {
+        let sk_file = self.key_file(login);
+        let not_readable_msg = format!("Could not read user key file: {}", &sk_file.display());
+
+        if !sk_file
+            .try_exists()
+            .map_err(|_| Error::Common(not_readable_msg.clone()))?
+        {
+            return Ok(None);
+        }
+
+        fs::read(&sk_file)
+            .map(Some)
+            .map_err(|_| Error::Common(not_readable_msg))
+    }
+
+    fn replace(&self, login: &AccountName, key: &[u8]) -> Result<(), Error> {
+        if !self.exists(login)? {
+            return Err(Error::BadLogin);
+        }
+        replace_key_file(&self.key_file(login), key)
+    }
+
+    fn list(&self) -> Result<Vec<AccountName>, Error> {
+        Ok(self
+            .account_dirs()?
+            .into_iter()
+            .filter(|(_raw, path)| path.join(crate::SK_FILENAME).exists()) // not watch-only
+            .filter_map(|(raw, _path)| {
+                AccountName::parse(&raw)
+                    .inspect_err(|e| warn!("Skipping account {:?}: {}", raw, e))
+                    .ok()
+            })
+            .collect())
+    }
+
+    fn delete(&self, login: &AccountName) -> Result<(), Error> {
+        let sk_dir = self.account_dir(login);
+        if sk_dir.try_exists().map_err(|_| {
+            Error::Common(format!(
+                "Could not check existence of {}.",
+                sk_dir.display()
+            ))
+        })? {
+            fs::remove_dir_all(&sk_dir)
+                .map_err(|e| Error::Common(format!("Could not remove {}: {}", sk_dir.display(), e)))?
+        }
+        Ok(())
+    }
+
+    fn rename(&self, from: &AccountName, to: &AccountName) -> Result<(), Error> {
+        let from_dir = self.account_dir(from);
+        let to_dir = self.account_dir(to);
+
+        if !self.exists(from)? {
+            return Err(Error::BadLogin);
+        }
+        if to_dir.exists() {
+            return Err(Error::AccountExists(to.to_string()));
+        }
+
+        fs::rename(&from_dir, &to_dir).map_err(|e| {
+            Error::Common(format!(
+                "Could not rename {} to {}: {}",
+                from_dir.display(),
+                to_dir.display(),
+                e
+            ))
+        })
+    }
+
+    fn trash(&self, login: &AccountName) -> Result<TrashEntry, Error> {
+        let sk_dir = self.account_dir(login);
+        if !sk_dir.exists() {
+            return Err(Error::BadLogin);
+        }
+
+        let entry = TrashEntry::new(login);
+        let trashed_dir = self.trash_dir.join(&entry.id);
+        fs::create_dir_all(&self.trash_dir).map_err(|e| {
+            Error::Common(format!(
+                "Could not create {}: {}",
+                self.trash_dir.display(),
+                e
+            ))
+        })?;
+        fs::rename(&sk_dir, &trashed_dir).map_err(|e| {
+            Error::Common(format!(
+                "Could not move {} to {}: {}",
+                sk_dir.display(),
+                trashed_dir.display(),
+                e
+            ))
+        })?;
+
+        Ok(entry)
+    }
+
+    fn list_trash(&self) -> Result<Vec<TrashEntry>, Error> {
+        Ok(subdirs(&self.trash_dir)?
+            .into_iter()
+            .filter_map(|(id, _path)| TrashEntry::from_id(&id))
+            .collect())
+    }
+
+    fn restore(&self, id: &str, login: &AccountName) -> Result<(), Error> {
+        let entry = TrashEntry::from_id(id)
+            .ok_or(Error::Common(format!("Unknown deleted account: {}", id)))?;
+        let trashed_dir = self.trash_dir.join(&entry.id);
+        let sk_dir = self.account_dir(login);
+
+        if !trashed_dir.exists() {
+            return Err(Error::Common(format!("Unknown deleted account: {}", id)));
+        }
+        if sk_dir.exists() {
+            return Err(Error::AccountExists(login.to_string()));
+        }
+
+        fs::create_dir_all(&self.accounts_dir).map_err(|e| {
+            Error::Common(format!(
+                "Could not create {}: {}",
+                self.accounts_dir.display(),
+                e
+            ))
+        })?;
+        fs::rename(&trashed_dir, &sk_dir).map_err(|e| {
+            Error::Common(format!(
+                "Could not move {} to {}: {}",
+                trashed_dir.display(),
+                sk_dir.display(),
+                e
+            ))
+        })
+    }
+
+    fn purge_trash(&self, before: u64) -> Result<(), Error> {
+        for entry in self.list_trash()? {
+            if entry.deleted_at < before {
+                let trashed_dir = self.trash_dir.join(&entry.id);
+                fs::remove_dir_all(&trashed_dir).map_err(|e| {
+                    Error::Common(format!("Could not remove {}: {}", trashed_dir.display(), e))
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn subdirs(dir: &PathBuf) -> Result<Vec<(String, PathBuf)>, Error> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    Ok(fs::read_dir(dir)
+        .map_err(|err| Error::Common(format!("Error reading {}. {}", dir.display(), err)))?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .map(|entry| (entry.file_name().to_string_lossy().to_string(), entry.path()))
+        .collect())
+}
+
+// Writes key file through a temporary file, so that the existing key is never left half-written.
+// Previous key file is kept as a backup until the new one is in place.
+fn replace_key_file(sk_file: &PathBuf, file_bytes: &[u8]) -> Result<(), Error> {
+    let tmp_file = sk_file.with_extension("key.tmp");
+    let backup_file = sk_file.with_extension("key.bak");
+
+    let write_tmp = || -> std::io::Result<()> {
+        let mut file = fs::File::create(&tmp_file)?;
+        file.write_all(file_bytes)?;
+        file.sync_all()
+    };
+    write_tmp().map_err(|e| {
+        let _ = fs::remove_file(&tmp_file);
+        Error::Common(format!(
+            "Could not write temporary key file {}: {}",
+            &tmp_file.display(),
+            e
+        ))
+    })?;
+
+    fs::copy(sk_file, &backup_file).map_err(|e| {
+        let _ = fs::remove_file(&tmp_file);
+        Error::Common(format!(
+            "Could not back up key file {}: {}",
+            &sk_file.display(),
+            e
+        ))
+    })?;
+
+    fs::rename(&tmp_file, sk_file).map_err(|e| {
+        let _ = fs::remove_file(&tmp_file);
+        Error::Common(format!(
+            "Could not replace key file {} (backup left in {}): {}",
+            &sk_file.display(),
+            &backup_file.display(),
+            e
+        ))
+    })?;
+
+    let _ = fs::remove_file(&backup_file)
+        .inspect_err(|e| warn!("Could not remove key backup {}: {}", &backup_file.display(), e));
+
+    Ok(())
+}
+
+// For tests, nothing touches the disk.
+#[derive(Default)]
+pub(crate) struct MemoryKeyStore {
+    keys: std::sync::Mutex<HashMap<AccountName, Vec<u8>>>,
+    trash: std::sync::Mutex<Vec<(TrashEntry, Vec<u8>)>>,
+}
+
+impl KeyStore for MemoryKeyStore {
+    fn create(&self, login: &AccountName, key: &[u8]) -> Result<(), Error> {
+        let mut keys = self.keys.lock().expect("Key store lock poisoned.");
+        if keys.contains_key(login) {
+            return Err(Error::AccountExists(login.to_string()));
+        }
+        keys.insert(login.clone(), key.to_vec());
+        Ok(())
+    }
+
+    fn load(&self, login: &AccountName) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self
+            .keys
+            .lock()
+            .expect("Key store lock poisoned.")
+            .get(login)
+            .cloned())
+    }
+
+    fn replace(&self, login: &AccountName, key: &[u8]) -> Result<(), Error> {
+        self.keys
+            .lock()
+            .expect("Key store lock poisoned.")
+            .get_mut(login)
+            .map(|k| *k = key.to_vec())
+            .ok_or(Error::BadLogin)
+    }
+
+    fn list(&self) -> Result<Vec<AccountName>, Error> {
+        Ok(self
+            .keys
+            .lock()
+            .expect("Key store lock poisoned.")
+            .keys()
+            .cloned()
+            .collect())
+    }
+
+    fn delete(&self, login: &AccountName) -> Result<(), Error> {
+        self.keys
+            .lock()
+            .expect("Key store lock poisoned.")
+            .remove(login);
+        Ok(())
+    }
+
+    fn rename(&self, from: &AccountName, to: &AccountName) -> Result<(), Error> {
+        let mut keys = self.keys.lock().expect("Key store lock poisoned.");
+        if keys.contains_key(to) {
+            return Err(Error::AccountExists(to.to_string()));
+        }
+        let key = keys.remove(from).ok_or(Error::BadLogin)?;
+        keys.insert(to.clone(), key);
+        Ok(())
+    }
+
+    fn trash(&self, login: &AccountName) -> Result<TrashEntry, Error> {
+        let key = self
+            .keys
+            .lock()
+            .expect("Key store lock poisoned.")
+            .remove(login)
+            .ok_or(Error::BadLogin)?;
+
+        let entry = TrashEntry::new(login);
+        self.trash
+            .lock()
+            .expect("Key store lock poisoned.")
+            .push((entry.clone(), key));
+        Ok(entry)
+    }
+
+    fn list_trash(&self) -> Result<Vec<TrashEntry>, Error> {
+        Ok(self
+            .trash
+            .lock()
+            .expect("Key store lock poisoned.")
+            .iter()
+            .map(|(entry, _key)| entry.clone())
+            .collect())
+    }
+
+    fn restore(&self, id: &str, login: &AccountName) -> Result<(), Error> {
+        let mut trash = self.trash.lock().expect("Key store lock poisoned.");
+        let position = trash
+            .iter()
+            .position(|(entry, _key)| entry.id == id)
+            .ok_or(Error::Common(format!("Unknown deleted account: {}", id)))?;
+
+        self.create(login, &trash[position].1)?;
+        trash.remove(position);
+        Ok(())
+    }
+
+    fn purge_trash(&self, before: u64) -> Result<(), Error> {
+        self.trash
+            .lock()
+            .expect("Key store lock poisoned.")
+            .retain(|(entry, _key)| entry.deleted_at >= before);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(name: &str) -> AccountName {
+        AccountName::parse(name).unwrap()
+    }
+
+    fn exercise(store: &dyn KeyStore) {
+        let (alice, bob, carol) = (name("alice"), name("bob"), name("carol"));
+
+        assert_eq!(None, store.load(&alice).unwrap());
+        store.create(&alice, &[1, 2]).unwrap();
+        assert!(store.create(&alice, &[3]).is_err());
+        assert_eq!(Some(vec![1, 2]), store.load(&alice).unwrap());
+
+        store.replace(&alice, &[3, 4]).unwrap();
+        assert_eq!(Some(vec![3, 4]), store.load(&alice).unwrap());
+        assert!(store.replace(&bob, &[5]).is_err());
+
+        store.create(&bob, &[5]).unwrap();
+        assert!(store.rename(&alice, &bob).is_err());
+        store.rename(&alice, &carol).unwrap();
+        let mut logins = store.list().unwrap();
+        logins.sort();
+        assert_eq!(vec![bob.clone(), carol.clone()], logins);
+
+        store.delete(&bob).unwrap();
+        store.delete(&bob).unwrap();
+        assert!(!store.exists(&bob).unwrap());
+        assert_eq!(Some(vec![3, 4]), store.load(&carol).unwrap());
+
+        let entry = store.trash(&carol).unwrap();
+        assert!(!store.exists(&carol).unwrap());
+        assert!(store.trash(&carol).is_err());
+        assert_eq!(vec![entry.clone()], store.list_trash().unwrap());
+
+        store.create(&carol, &[6]).unwrap();
+        assert!(store.restore(&entry.id, &carol).is_err()); // name taken meanwhile
+        store.restore(&entry.id, &alice).unwrap();
+        assert_eq!(Some(vec![3, 4]), store.load(&alice).unwrap());
+        assert!(store.list_trash().unwrap().is_empty());
+
+        store.trash(&carol).unwrap();
+        store.create(&carol, &[7]).unwrap();
+        store.trash(&carol).unwrap(); // same name, likely within the same second
+        store.purge_trash(0).unwrap();
+        assert_eq!(2, store.list_trash().unwrap().len());
+        store.purge_trash(crate::profile::now() + 1).unwrap();
+        assert!(store.list_trash().unwrap().is_empty());
+    }
+
+    #[test]
+    fn trash_ids() {
+        let alice = AccountName::parse("alice").unwrap();
+        let entry = TrashEntry::new(&alice);
+        assert_eq!(Some(entry.clone()), TrashEntry::from_id(&entry.id));
+        assert_ne!(entry.id, TrashEntry::new(&alice).id);
+
+        let old = TrashEntry::from_id("1700000000_alice").unwrap(); // by older versions
+        assert_eq!((alice, 1700000000), (old.login, old.deleted_at));
+    }
+
+    fn test_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "jams-keystore-{}-{}",
+            test,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn memory_store() {
+        exercise(&MemoryKeyStore::default());
+    }
+
+    #[test]
+    fn file_store() {
+        let dir = test_dir("store");
+        exercise(&FileKeyStore::new(dir.join("accounts"), dir.join("trash")));
+
+        // watch-only account, directory without a key
+        let store = FileKeyStore::new(dir.join("accounts"), dir.join("trash"));
+        let dave = AccountName::parse("dave").unwrap();
+        fs::create_dir_all(store.account_dir(&dave)).unwrap();
+        assert!(!store.exists(&dave).unwrap());
+        assert!(!store.list().unwrap().contains(&dave));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn legacy_names_migration() {
+        let dir = test_dir("migration");
+        for legacy in ["ok", "con", "con_", " spaced ", ".hidden"] {
+            fs::create_dir_all(dir.join(legacy)).unwrap();
+            fs::write(dir.join(legacy).join(crate::SK_FILENAME), legacy).unwrap();
+        }
+
+        let store = FileKeyStore::new(dir.clone(), dir.join("trash"));
+        store.migrate_legacy_names().unwrap();
+        store.migrate_legacy_names().unwrap(); // idempotent
+
+        let mut logins = store.list().unwrap();
+        logins.sort();
+        assert_eq!(
+            vec![name("con_"), name("con_-2"), name("hidden"), name("ok"), name("spaced")],
+            logins
+        );
+        assert_eq!(Some(b"con".to_vec()), store.load(&name("con_-2")).unwrap());
+        assert_eq!(Some(b" spaced ".to_vec()), store.load(&name("spaced")).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -5,44 +5,245 @@ use lofty::picture::{MimeType, Picture, PictureType};
 use lofty::prelude::{ItemKey, TaggedFileExt};
 use lofty::read_from_path;
 use lofty::tag::{Accessor, Tag, TagExt};
-use safeapi::{Network, Safe, XorNameBuilder, Multiaddr, SecretKey, XorName};
+use safeapi::{Safe, XorNameBuilder, Multiaddr, SecretKey, XorName};
 use serde::{Deserialize, Serialize};
 use std::{fs, io::Cursor, path::PathBuf};
 use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{debug, error, info, warn};
 
 mod frontend;
 use frontend::*;
 
+mod bundle;
+use bundle::AccountBundle;
+
+mod keys;
+
+mod account_name;
+use account_name::AccountName;
+
+mod keystore;
+use keystore::{FileKeyStore, KeyStore, TrashEntry};
+
+mod profile;
+use profile::AccountProfile;
+
+mod wallet;
+use wallet::WalletState;
+
+mod network;
+use network::NetworkSetting;
+
+mod backend;
+use backend::Client;
+
+mod connection;
+use connection::{Connection, ConnectionState, HealthCheck};
+
+mod diagnostics;
+use diagnostics::{Diagnostics, NetworkDiagnostics, Operation};
+
+mod operations;
+use operations::InFlight;
+
+mod queue;
+use queue::{OfflineQueue, QueueItem, QueuedOp};
+
+mod logging;
+use logging::Logging;
+
+mod registers;
+use registers::{Envelope, RegisterHistory, RegisterKey, RegisterVersion, Schema};
+
+mod merge;
+
 #[cfg(target_os = "linux")]
 mod server;
 
 
 const ACCOUNTS_DIR: &str = "accounts";
+const QUEUE_DIR: &str = "queue"; // network writes made while offline
+const TRASH_DIR: &str = "trash"; // deleted accounts
+const TRASH_RETENTION_SECS: u64 = 30 * 24 * 60 * 60;
 const SK_FILENAME: &str = "sk.key";
 const ADDRESS_FILENAME: &str = "evm_address";
+const WATCH_ONLY_FILENAME: &str = "watch_only"; // marker, such account has no SK_FILENAME
 
 const DEFAULT_LOG_LEVEL: &str = "INFO";
+const DEFAULT_MNEMONIC_WORDS: usize = 12;
+const LOCK_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
 
 
-#[derive(Debug, Serialize, Deserialize)]
+// Serialized for frontend as {code, message, details?}. Codes are stable, frontend decides on them.
+#[derive(Debug)]
 enum Error {
     Common(String),
     BadLogin,
     BadPassword,
+    BadAccountName(String),
+    BadMnemonic(keys::MnemonicError),
     NotConnected,
+    AlreadyConnecting,
+    ConnectTimeout(u64), // seconds
+    ConnectCancelled,
+    Disconnecting, // no new network operations meanwhile
+    Aborted,       // network operation cancelled by disconnect
+    Locked,
+    WatchOnly,
+    InsufficientFunds, // tokens to pay for storage
+    InsufficientGas,   // to pay for payment transaction
+    NotFound(String),  // data or register on the network
+    Timeout(String),   // network operation, not connecting
+    InvalidAddress(String),
+    FileIo { path: String, reason: String },
+    TagRead(String), // file path
+    TagWrite(String),
+    Network(String),
+    InvalidDocument(String), // register data not matching its schema
+    Conflict { revision: u32, current: Box<Envelope> }, // register changed since read
+    WrongAccount(String), // private register of another account, its address
+    NotSupported(String), // by the network library
+    AccountExists(String), // username
+    AlreadyImported(String), // username, the same account is already there
+    BundleMismatch { address: String, key_address: String }, // bundle not matching its key
+}
+
+impl Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Error::Common(_) => "Common",
+            Error::BadLogin => "BadLogin",
+            Error::BadPassword => "BadPassword",
+            Error::BadAccountName(_) => "BadAccountName",
+            Error::BadMnemonic(_) => "BadMnemonic",
+            Error::NotConnected => "NotConnected",
+            Error::AlreadyConnecting => "AlreadyConnecting",
+            Error::ConnectTimeout(_) => "ConnectTimeout",
+            Error::ConnectCancelled => "ConnectCancelled",
+            Error::Disconnecting => "Disconnecting",
+            Error::Aborted => "Aborted",
+            Error::Locked => "Locked",
+            Error::WatchOnly => "WatchOnly",
+            Error::InsufficientFunds => "InsufficientFunds",
+            Error::InsufficientGas => "InsufficientGas",
+            Error::NotFound(_) => "NotFound",
+            Error::Timeout(_) => "Timeout",
+            Error::InvalidAddress(_) => "InvalidAddress",
+            Error::FileIo { .. } => "FileIo",
+            Error::TagRead(_) => "TagRead",
+            Error::TagWrite(_) => "TagWrite",
+            Error::Network(_) => "Network",
+            Error::InvalidDocument(_) => "InvalidDocument",
+            Error::Conflict { .. } => "Conflict",
+            Error::WrongAccount(_) => "WrongAccount",
+            Error::NotSupported(_) => "NotSupported",
+            Error::AccountExists(_) => "AccountExists",
+            Error::AlreadyImported(_) => "AlreadyImported",
+            Error::BundleMismatch { .. } => "BundleMismatch",
+        }
+    }
+
+    // machine readable data, besides the code
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            Error::BadMnemonic(e) => serde_json::to_value(e).ok(),
+            Error::ConnectTimeout(secs) => Some(serde_json::json!({ "seconds": secs })),
+            Error::FileIo { path, .. } | Error::TagRead(path) | Error::TagWrite(path) => {
+                Some(serde_json::json!({ "path": path }))
+            }
+            Error::InvalidAddress(address) | Error::WrongAccount(address) => {
+                Some(serde_json::json!({ "address": address }))
+            }
+            Error::AccountExists(login) | Error::AlreadyImported(login) => {
+                Some(serde_json::json!({ "login": login }))
+            }
+            Error::BundleMismatch {
+                address,
+                key_address,
+            } => Some(serde_json::json!({ "address": address, "keyAddress": key_address })),
+            Error::Conflict { revision, current } => {
+                Some(serde_json::json!({ "revision": revision, "current": current }))
+            }
+            _ => None,
+        }
+    }
+
+    fn file_io(path: &std::path::Path, e: std::io::Error) -> Self {
+        Error::FileIo {
+            path: path.display().to_string(),
+            reason: e.to_string(),
+        }
+    }
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             crate::Error::Common(msg) => f.write_str(&msg),
-            _ => write!(f, "{:?}", self),
+            crate::Error::BadLogin => f.write_str("Wrong username or password."),
+            crate::Error::BadPassword => f.write_str("Wrong password."),
+            crate::Error::BadAccountName(reason) => write!(f, "Invalid account name: {}", reason),
+            crate::Error::BadMnemonic(e) => write!(f, "Invalid recovery phrase: {:?}", e),
+            crate::Error::NotConnected => f.write_str("Not connected to the network."),
+            crate::Error::AlreadyConnecting => f.write_str("Already connecting."),
+            crate::Error::ConnectTimeout(secs) => {
+                write!(f, "Connecting timed out after {} seconds.", secs)
+            }
+            crate::Error::ConnectCancelled => f.write_str("Connecting cancelled."),
+            crate::Error::Disconnecting => f.write_str("Disconnecting from the network."),
+            crate::Error::Aborted => f.write_str("Aborted by disconnect."),
+            crate::Error::Locked => f.write_str("Wallet is locked."),
+            crate::Error::WatchOnly => f.write_str("Watch-only account cannot pay."),
+            crate::Error::InsufficientFunds => f.write_str("Not enough tokens to pay for storage."),
+            crate::Error::InsufficientGas => f.write_str("Not enough gas to pay for the transaction."),
+            crate::Error::NotFound(what) => write!(f, "Not found: {}", what),
+            crate::Error::Timeout(what) => write!(f, "Timed out: {}", what),
+            crate::Error::InvalidAddress(address) => write!(f, "Invalid address: {}", address),
+            crate::Error::FileIo { path, reason } => write!(f, "File {}: {}", path, reason),
+            crate::Error::TagRead(path) => write!(f, "Cannot read tags from file {}", path),
+            crate::Error::TagWrite(path) => write!(f, "Cannot save tags to file {}", path),
+            crate::Error::Network(reason) => write!(f, "Network: {}", reason),
+            crate::Error::InvalidDocument(reason) => write!(f, "Invalid document: {}", reason),
+            crate::Error::Conflict { revision, .. } => {
+                write!(f, "Register was changed meanwhile, it is at revision {} now.", revision)
+            }
+            crate::Error::WrongAccount(owner) => {
+                write!(f, "Register is private to another account ({}).", owner)
+            }
+            crate::Error::NotSupported(what) => write!(f, "Not supported: {}", what),
+            crate::Error::AccountExists(login) => write!(f, "Account {} already exists.", login),
+            crate::Error::AlreadyImported(login) => {
+                write!(f, "Account {} is already imported.", login)
+            }
+            crate::Error::BundleMismatch {
+                address,
+                key_address,
+            } => write!(
+                f,
+                "Bundle address {} does not match its key ({}).",
+                address, key_address
+            ),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
+impl Serialize for Error {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let details = self.details();
+        let mut error = serializer.serialize_struct("Error", 2 + details.is_some() as usize)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        if let Some(details) = details {
+            error.serialize_field("details", &details)?;
+        }
+        error.end()
+    }
+}
+
 impl From<String> for Error {
     fn from(err: String) -> Self {
         Self::Common(err)
@@ -55,12 +256,54 @@ impl From<tauri::Error> for Error {
     }
 }
 
+// safeapi passes autonomi and evm errors on as Custom messages (connect, put, get, graph,
+// pointer, evm wallet and token, decode), which can't be told apart. Those are Network.
+// Failed payments are recognized by backend, from the wallet balance.
 impl From<safeapi::Error> for Error {
     fn from(safe_error: safeapi::Error) -> Self {
-        Self::Common(format!("Safe: {}", safe_error))
+        match safe_error {
+            safeapi::Error::NotConnected => Error::NotConnected,
+            safeapi::Error::NotLoggedIn => Error::BadLogin, // no wallet, nobody signed in
+            safeapi::Error::BadPassword => Error::BadPassword,
+            safeapi::Error::SecretKeyEncryption(reason) => {
+                Error::Common(format!("Key encryption: {}", reason))
+            }
+            safeapi::Error::Custom(message) => classify_safe_message(message),
+        }
+    }
+}
+
+// Autonomi and EVM errors reach us only as safeapi's Custom messages.
+fn classify_safe_message(message: String) -> Error {
+    let lower = message.to_lowercase();
+    let has = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+
+    if has(&["insufficient funds for gas", "gas required exceeds", "not enough gas"]) {
+        Error::InsufficientGas
+    } else if has(&[
+        "error occurred during payment",
+        "insufficient balance",
+        "insufficient funds",
+        "not enough balance",
+    ]) {
+        Error::InsufficientFunds
+    } else if has(&["not found", "no such record"]) {
+        Error::NotFound(message)
+    } else if has(&["timed out", "timeout"]) {
+        Error::Timeout(message)
+    } else {
+        Error::Network(message)
     }
 }
 
+fn parse_xorname(xorname: &str) -> Result<XorName, Error> {
+    hex::decode(xorname)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(XorName)
+        .ok_or(Error::InvalidAddress(String::from(xorname)))
+}
+
 fn make_root(app: &mut AppHandle) -> Result<PathBuf, Error> {
     let app_data = app
         .path()
@@ -76,7 +319,7 @@ fn make_root(app: &mut AppHandle) -> Result<PathBuf, Error> {
     Ok(app_data)
 }
 
-fn user_root(app_root: &PathBuf, login: String) -> PathBuf {
+fn user_root(app_root: &PathBuf, login: &AccountName) -> PathBuf {
     let mut sk_dir = app_root.clone();
     sk_dir.push(ACCOUNTS_DIR);
     sk_dir.push(login);
@@ -84,29 +327,28 @@ fn user_root(app_root: &PathBuf, login: String) -> PathBuf {
     sk_dir
 }
 
+// Account files don't rely on the key store having made the directory.
+fn create_user_root(app_root: &PathBuf, login: &AccountName) -> Result<PathBuf, Error> {
+    let dir = user_root(app_root, login);
+    fs::create_dir_all(&dir)
+        .map_err(|_| Error::Common(format!("Could not create user dir: {}", &dir.display())))?;
+
+    Ok(dir)
+}
+
 fn load_create_import_key(
-    app_root: &PathBuf,
-    login: String,
+    key_store: &dyn KeyStore,
+    login: &AccountName,
     password: String,
     eth_pk: Option<String>, // if you want to import ethereum private key during registration
     register: bool,
 ) -> Result<String, Error> {
-    let sk_dir = user_root(app_root, login);
-    let mut sk_file = sk_dir.clone();
-    sk_file.push(SK_FILENAME);
-
-    let not_readable_msg = format!("Could not read user key file: {}", &sk_file.display());
-    let eth_pk = if sk_file
-        .try_exists()
-        .map_err(|_| Error::Common(not_readable_msg.clone()))?
-    {
+    let eth_pk = if let Some(bytes) = key_store.load(login)? {
         if register {
-            return Err(Error::Common(String::from("User already exists")));
+            return Err(Error::AccountExists(login.to_string()));
         }
 
-        let bytes = fs::read(&sk_file).map_err(|_| Error::Common(not_readable_msg.clone()))?;
-
-        Safe::decrypt_eth(&bytes, &password)?
+        Safe::decrypt_eth(&bytes, &password).map_err(|_| Error::BadPassword)?
     } else {
         if !register {
             return Err(Error::BadLogin);
@@ -115,146 +357,473 @@ fn load_create_import_key(
         let pk = eth_pk.unwrap_or(SecretKey::random().to_hex()); // bls secret key can be used as eth privkey
 
         let file_bytes = Safe::encrypt_eth(pk.clone(), &password)?;
-        fs::create_dir_all(sk_dir.clone()).map_err(|_| {
-            Error::Common(format!("Could not create user dir: {}", &sk_dir.display()))
-        })?;
-        fs::write(&sk_file, file_bytes).map_err(|_| {
-            Error::Common(format!(
-                "Could not save user key file: {}",
-                &sk_file.display()
-            ))
-        })?;
+        key_store.create(login, &file_bytes)?;
         pk
     };
 
     Ok(eth_pk)
 }
 
+fn change_key_password(
+    key_store: &dyn KeyStore,
+    login: &AccountName,
+    old_password: &str,
+    new_password: &str,
+) -> Result<(), Error> {
+    let bytes = key_store.load(login)?.ok_or(Error::BadLogin)?;
+    let pk = Safe::decrypt_eth(&bytes, old_password).map_err(|_| Error::BadPassword)?;
+
+    let file_bytes = Safe::encrypt_eth(pk.clone(), new_password)?;
+    // make sure the new file decrypts before touching the old one
+    if Safe::decrypt_eth(&file_bytes, new_password)? != pk {
+        return Err(Error::Common(String::from("Re-encrypted key does not match.")));
+    }
+
+    key_store.replace(login, &file_bytes)
+}
+
 #[tauri::command]
-async fn list_accounts(mut app: AppHandle) -> Result<Vec<(String, String)>, Error> {
-    let mut accounts_dir = make_root(&mut app)
+fn change_password(
+    login: String,
+    old_password: String,
+    new_password: String,
+    key_store: State<'_, Box<dyn KeyStore>>,
+) -> Result<(), Error> {
+    change_key_password(
+        key_store.as_ref(),
+        &AccountName::parse(&login)?,
+        &old_password,
+        &new_password,
+    )
+}
+
+#[tauri::command]
+fn export_account(
+    login: String,
+    password: String,
+    destination: String, // bundle file path
+    key_store: State<'_, Box<dyn KeyStore>>,
+    mut app: AppHandle,
+) -> Result<(), Error> {
+    let login = AccountName::parse(&login)?;
+    let key_bytes = key_store.load(&login)?.ok_or(Error::BadLogin)?;
+    let pk = Safe::decrypt_eth(&key_bytes, &password).map_err(|_| Error::BadPassword)?;
+    let address = keys::eth_address(&pk)?;
+
+    let account_dir = user_root(&make_root(&mut app)?, &login);
+    let profile = AccountProfile::load(&account_dir)?
+        .unwrap_or_else(|| AccountProfile::from_legacy(login.to_string(), &account_dir));
+    let bundle = AccountBundle::new(login.to_string(), address, &key_bytes, profile);
+    let path = PathBuf::from(destination);
+    fs::write(&path, bundle.to_bytes()?).map_err(|e| {
+        Error::Common(format!(
+            "Could not save account bundle {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    Ok(())
+}
+
+// returns username and address of the imported account
+#[tauri::command]
+fn import_account(
+    file: String,          // bundle file path
+    password: String,      // password of the exported account
+    login: Option<String>, // import under a different username
+    key_store: State<'_, Box<dyn KeyStore>>,
+    mut app: AppHandle,
+) -> Result<(AccountName, String), Error> {
+    let path = PathBuf::from(file);
+    let bytes = fs::read(&path)
+        .map_err(|e| Error::file_io(&path, e))?;
+    let bundle = AccountBundle::from_bytes(&bytes)?;
+
+    let key_bytes = bundle.encrypted_key()?;
+    let pk = Safe::decrypt_eth(&key_bytes, &password).map_err(|_| Error::BadPassword)?;
+    let address = keys::eth_address(&pk)?;
+    if !keys::same_address(&address, &bundle.address) {
+        return Err(Error::BundleMismatch {
+            address: bundle.address,
+            key_address: address,
+        });
+    }
+
+    let login = AccountName::parse(&login.unwrap_or(bundle.username))?;
+    let app_root = make_root(&mut app)?;
+    let addr_file = user_root(&app_root, &login).join(ADDRESS_FILENAME);
+
+    if key_store.exists(&login)? {
+        let existing = fs::read_to_string(&addr_file).unwrap_or_default();
+        if keys::same_address(&existing, &address) {
+            return Err(Error::AlreadyImported(login.to_string()));
+        }
+        return Err(Error::AccountExists(login.to_string()));
+    }
+
+    key_store.create(&login, &key_bytes)?;
+    let account_dir = create_user_root(&app_root, &login)?;
+    fs::write(&addr_file, &address).map_err(|_| {
+        Error::Common(format!(
+            "Could not save address file: {}",
+            &addr_file.display()
+        ))
+    })?;
+    // kept from the exporting machine, bundles without a profile keep at least creation time
+    let profile = bundle.profile.unwrap_or_else(|| AccountProfile {
+        created_at: bundle.created_at,
+        ..AccountProfile::new(login.to_string())
+    });
+    profile.save(&account_dir)?;
+
+    Ok((login, address))
+}
+
+// returns accounts sorted from most recently used
+#[tauri::command]
+async fn list_accounts(
+    key_store: State<'_, Box<dyn KeyStore>>,
+    mut app: AppHandle,
+) -> Result<Vec<AccountSummary>, Error> {
+    let app_root = make_root(&mut app)
         .map_err(|_| Error::Common(format!("Cannot access/create application folder.")))?;
-    accounts_dir.push(ACCOUNTS_DIR);
-
-    let default_mod_time = std::time::SystemTime::now();
-
-    let mut entries = fs::read_dir(accounts_dir)
-        .map_err(|err| Error::Common(format!("Error reading accounts. {}", err)))?
-        .filter(Result::is_ok)
-        .map(Result::unwrap)
-        .filter(|entry| entry.file_type().unwrap().is_dir())
-        .map(|entry| {
-            let address_file = entry.path().join(ADDRESS_FILENAME);
-
-            let modified = address_file
-                .metadata()
-                .map(|m| m.modified().unwrap_or(default_mod_time))
-                .unwrap_or(default_mod_time);
-            let username = entry.file_name().to_string_lossy().to_string();
-            let address = fs::read_to_string(&address_file)
-                .inspect_err(|err| eprintln!("Error reading address file. {}", err))
-                .unwrap_or(String::from("<error>"));
-
-            (modified, username, address)
+
+    let mut accounts = key_store
+        .list()?
+        .into_iter()
+        .chain(watch_only_accounts(&app_root))
+        .map(|username| {
+            let account_dir = user_root(&app_root, &username);
+
+            let address = fs::read_to_string(account_dir.join(ADDRESS_FILENAME))
+                .inspect_err(|err| warn!("Error reading address file. {}", err))
+                .ok();
+            let profile = AccountProfile::load(&account_dir)
+                .inspect_err(|err| warn!("Error reading profile. {}", err))
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| AccountProfile::from_legacy(username.to_string(), &account_dir));
+
+            AccountSummary {
+                username,
+                address,
+                watch_only: account_dir.join(WATCH_ONLY_FILENAME).exists(),
+                profile,
+            }
         })
-        .collect::<Vec<(std::time::SystemTime, String, String)>>();
+        .collect::<Vec<AccountSummary>>();
+
+    // never used accounts (None) go last
+    accounts.sort_by(|a, b| b.profile.last_sign_in_at.cmp(&a.profile.last_sign_in_at));
 
-    entries.sort(); // sort by "modified"
+    Ok(accounts)
+}
 
-    Ok(entries
-        .iter()
-        .map(|(_modified, username, address)| (username.clone(), address.clone()))
-        .collect::<Vec<(String, String)>>())
+// the key store doesn't know accounts without a key
+fn watch_only_accounts(app_root: &PathBuf) -> Vec<AccountName> {
+    let Ok(entries) = fs::read_dir(app_root.join(ACCOUNTS_DIR)) else {
+        return vec![];
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().join(WATCH_ONLY_FILENAME).exists())
+        .filter_map(|entry| AccountName::parse(&entry.file_name().to_string_lossy()).ok())
+        .collect()
 }
 
-// leave peer empty or anything other than Multiaddr to connect to official network.
 #[tauri::command]
-async fn connect(peer: Option<String>, app: AppHandle) -> Result<(), Error> {
-    let state = app.try_state::<Mutex<Option<Safe>>>();
-    if state.is_some() {
-        return if state.unwrap().lock().await.is_some() {
-            println!("Already connected.");
-            Ok(())
-        } else {
-            println!("Already connecting...");
-            Err(Error::Common(String::from("Already connecting.")))
-        };
+fn update_account_profile(
+    login: String,
+    display_name: Option<String>,
+    preferred_network: Option<NetworkSetting>,
+    avatar: Option<String>,
+    mut app: AppHandle,
+) -> Result<AccountProfile, Error> {
+    let login = AccountName::parse(&login)?;
+    if let Some(network) = &preferred_network {
+        network.validate()?;
     }
-    app.manage(Mutex::new(None::<Safe>));
+    let account_dir = user_root(&make_root(&mut app)?, &login);
+    let mut profile = AccountProfile::load(&account_dir)?.ok_or(Error::BadLogin)?;
 
-	let network = match peer {
-		Some(peer_str) => Network::Local(vec![peer_str]),
-//		None => Network::Alpha,
-		None => Network::Mainnet,
-	};
+    display_name.inspect(|name| profile.display_name = name.clone());
+    preferred_network.inspect(|network| profile.preferred_network = Some(network.clone()));
+    avatar.inspect(|avatar| profile.avatar = Some(avatar.clone()));
 
-    println!("Connecting {:?} ...", network);
+    profile.save(&account_dir)?;
+    Ok(profile)
+}
 
-    let safe = Safe::connect(network, None, DEFAULT_LOG_LEVEL)
-        .await
-        .inspect_err(|_| {
-            app.unmanage::<Mutex<Option<Safe>>>();
-        })?;
+// leave network empty to connect to the last used one (Mainnet at first).
+#[tauri::command]
+async fn connect(network: Option<NetworkSetting>, mut app: AppHandle) -> Result<(), Error> {
+    if let Some(network) = &network {
+        network.validate()?;
+    }
+    if !connection::connect(&app, network.clone()).await? {
+        return Ok(()); // already connected to it
+    }
 
-    println!("\n\nConnected.");
+    // kept only once it connects
+    if let Some(network) = &network {
+        network.save(&make_root(&mut app)?)?;
+    }
 
     // Emit the connect event with the extracted address
     let _ = app
         .emit("connected", ())
-        .inspect_err(|e| eprintln!("{}", e));
+        .inspect_err(|e| error!("{}", e));
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn cancel_connect(app: AppHandle) -> Result<(), Error> {
+    connection::cancel(&app).await
+}
+
+#[tauri::command]
+async fn set_connect_timeout(seconds: u64, app: AppHandle) -> Result<(), Error> {
+    if seconds == 0 {
+        return Err(Error::Common(String::from("Connect timeout must be positive.")));
+    }
+    app.state::<Mutex<Connection>>().lock().await.connect_timeout =
+        std::time::Duration::from_secs(seconds);
+    Ok(())
+}
 
-    // Store the `safe` object in the application's state
-    *(app.state::<Mutex<Option<Safe>>>().lock().await) = Some(safe);
+#[tauri::command]
+async fn network_setting(app: AppHandle) -> NetworkSetting {
+    app.state::<Mutex<Connection>>().lock().await.network.clone()
+}
 
+// chooses network for the next connect, without connecting
+#[tauri::command]
+async fn set_network(network: NetworkSetting, mut app: AppHandle) -> Result<(), Error> {
+    network.validate()?;
+    network.save(&make_root(&mut app)?)?;
+    app.state::<Mutex<Connection>>().lock().await.network = network;
     Ok(())
 }
 
 #[tauri::command]
+async fn network_diagnostics(app: AppHandle) -> NetworkDiagnostics {
+    diagnostics::collect(&app).await
+}
+
+// saves diagnostics report to attach to a bug report
+#[tauri::command]
+async fn export_diagnostics(destination: String, app: AppHandle) -> Result<(), Error> {
+    let report = serde_json::json!({
+        "appVersion": app.package_info().version.to_string(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "generatedAt": profile::now(),
+        "network": diagnostics::collect(&app).await,
+    });
+    let bytes = serde_json::to_vec_pretty(&report)
+        .map_err(|e| Error::Common(format!("Could not serialize diagnostics: {}", e)))?;
+
+    let path = PathBuf::from(destination);
+    fs::write(&path, bytes).map_err(|e| {
+        Error::Common(format!(
+            "Could not save diagnostics {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+#[tauri::command]
+async fn connection_status(app: AppHandle) -> ConnectionState {
+    connection::state(&app).await
+}
+
+#[tauri::command]
+// returns mnemonic phrase, if a new key was generated during registration
 async fn sign_in(
     login: String,
     password: String,
     eth_pk_import: Option<String>,
+    mnemonic_words: Option<usize>, // 12 or 24
     register: bool,
     mut app: AppHandle,
+) -> Result<Option<String>, Error> {
+    let app_root = make_root(&mut app)?;
+    let login = AccountName::parse(&login)?;
+
+    let mnemonic = if register && eth_pk_import.is_none() {
+        Some(keys::generate_mnemonic(
+            mnemonic_words.unwrap_or(DEFAULT_MNEMONIC_WORDS),
+        )?)
+    } else {
+        None
+    };
+    let eth_pk_import = match &mnemonic {
+        Some(mnemonic) => Some(keys::mnemonic_to_eth_pk(mnemonic)?),
+        None => eth_pk_import,
+    };
+
+    // connected before a new key is stored, so its phrase can't get lost on failure
+    let safe = app.try_state::<Client>().ok_or(Error::NotConnected)?;
+    let mut safe = safe.lock().await;
+    let client = safe.as_mut().ok_or(Error::NotConnected)?;
+
+    let key_store = app.state::<Box<dyn KeyStore>>();
+    let pk = load_create_import_key(
+        key_store.as_ref(),
+        &login,
+        password,
+        eth_pk_import,
+        register,
+    )?;
+
+    let signed_in = (|| {
+        client.login_with_eth(Some(pk.clone()))?; // sign in
+        let address = client.address()?;
+
+        // Prepare the address directory and file
+        let addr_dir = create_user_root(&app_root, &login)?;
+        let mut addr_file = addr_dir.clone();
+        addr_file.push(ADDRESS_FILENAME);
+
+        // Write the address to a file
+        fs::write(&addr_file, &address).map_err(|_| {
+            Error::Common(format!(
+                "Could not save address file: {}",
+                &addr_file.display()
+            ))
+        })?;
+
+        let mut profile = AccountProfile::load(&addr_dir)?
+            .unwrap_or_else(|| AccountProfile::new(login.to_string()));
+        profile.last_sign_in_at = Some(profile::now());
+        profile.save(&addr_dir)?;
+        Ok(address)
+    })();
+    let address = match signed_in {
+        Ok(address) => address,
+        Err(e) => {
+            if register {
+                // the user never sees the phrase of the new key, don't keep the account
+                let _ = client.login_with_eth(None);
+                let _ = key_store
+                    .delete(&login)
+                    .inspect_err(|e| error!("Could not remove account {}: {}", login, e));
+            }
+            return Err(e);
+        }
+    };
+    drop(safe);
+    info!(login = %login, address = %address, "Signed in.");
+
+    app.state::<Mutex<WalletState>>()
+        .lock()
+        .await
+        .signed_in(login.clone(), pk);
+
+    session_set(
+        String::from(USER_SESSION_KEY),
+        Some(
+            serde_json::to_string(&SimpleAccountUser {
+                username: login.to_string(),
+                address: address,
+            })
+            .expect("Object values should be able to serialize."),
+        ),
+        app.clone(),
+    )
+    .await;
+
+    let _ = app.emit("sign_in", ()).inspect_err(|e| error!("{}", e));
+    queue::schedule_replay(&app); // what was queued while offline
+
+    Ok(mnemonic.map(|m| m.to_string()))
+}
+
+// registers an account with a key derived from BIP39 mnemonic phrase
+#[tauri::command]
+async fn recover_account(
+    login: String,
+    password: String,
+    mnemonic: String,
+    app: AppHandle,
+) -> Result<(), Error> {
+    let mnemonic = keys::parse_mnemonic(&mnemonic).map_err(Error::BadMnemonic)?;
+    let pk = keys::mnemonic_to_eth_pk(&mnemonic)?;
+
+    sign_in(login, password, Some(pk), None, true, app).await?;
+    Ok(())
+}
+
+// Account with just an address, for browsing public data of other users.
+#[tauri::command]
+fn add_watch_only_account(
+    login: String,
+    address: String,
+    display_name: Option<String>,
+    mut app: AppHandle,
 ) -> Result<(), Error> {
+    let login = AccountName::parse(&login)?;
+    if !keys::is_eth_address(&address) {
+        return Err(Error::InvalidAddress(address));
+    }
+
     let app_root = make_root(&mut app)?;
+    if user_root(&app_root, &login).exists() {
+        return Err(Error::AccountExists(login.to_string()));
+    }
+    let account_dir = create_user_root(&app_root, &login)?;
+
+    let write = |name: &str, contents: &str| {
+        let file = account_dir.join(name);
+        fs::write(&file, contents)
+            .map_err(|_| Error::Common(format!("Could not save {}", file.display())))
+    };
+    write(WATCH_ONLY_FILENAME, "")?;
+    write(ADDRESS_FILENAME, &address)?;
+    AccountProfile::new(display_name.unwrap_or(login.to_string())).save(&account_dir)?;
+
+    Ok(())
+}
 
-    let pk = load_create_import_key(&app_root, login.clone(), password, eth_pk_import, register)?;
-    println!("\n\nEth Private Key: {:.4}(...)", pk);
+// Watch-only sign in: no password, and the client gets no spendable wallet.
+#[tauri::command]
+async fn sign_in_watch_only(login: String, mut app: AppHandle) -> Result<(), Error> {
+    let login = AccountName::parse(&login)?;
+    let account_dir = user_root(&make_root(&mut app)?, &login);
+    if !account_dir.join(WATCH_ONLY_FILENAME).exists() {
+        return Err(Error::BadLogin);
+    }
 
-    app.try_state::<Mutex<Option<Safe>>>()
+    let address = fs::read_to_string(account_dir.join(ADDRESS_FILENAME))
+        .map_err(|_| Error::Common(format!("Could not read address of {}", login)))?;
+
+    // don't leave previous user's key usable
+    app.try_state::<Client>()
         .ok_or(Error::NotConnected)?
         .lock()
         .await
         .as_mut()
-        .ok_or(Error::NotConnected)? // not signed in
-        .login_with_eth(Some(pk))?; // sign in
-
-    let address = client_address(
-        app.try_state::<Mutex<Option<Safe>>>()
-            .ok_or(Error::NotConnected)?,
-    )
-    .await?;
-    println!("ETH wallet address: {}", address);
+        .ok_or(Error::NotConnected)?
+        .login_with_eth(None)?;
 
-    // Prepare the address directory and file
-    let addr_dir = user_root(&app_root, login.clone());
-    let mut addr_file = addr_dir.clone();
-    addr_file.push(ADDRESS_FILENAME);
+    let mut profile = AccountProfile::load(&account_dir)?
+        .unwrap_or_else(|| AccountProfile::new(login.to_string()));
+    profile.last_sign_in_at = Some(profile::now());
+    profile.save(&account_dir)?;
 
-    // Write the address to a file
-    fs::write(&addr_file, &address).map_err(|_| {
-        Error::Common(format!(
-            "Could not save address file: {}",
-            &addr_file.display()
-        ))
-    })?;
+    app.state::<Mutex<WalletState>>()
+        .lock()
+        .await
+        .signed_in_watch_only(login.clone(), address.clone());
 
     session_set(
         String::from(USER_SESSION_KEY),
         Some(
             serde_json::to_string(&SimpleAccountUser {
-                username: login,
+                username: login.to_string(),
                 address: address,
             })
             .expect("Object values should be able to serialize."),
@@ -263,44 +832,155 @@ async fn sign_in(
     )
     .await;
 
-    let _ = app.emit("sign_in", ()).inspect_err(|e| eprintln!("{}", e));
+    let _ = app.emit("sign_in", ()).inspect_err(|e| error!("{}", e));
 
     Ok(())
 }
 
 #[tauri::command]
-async fn is_connected(app: AppHandle) -> bool {
-    let safe = app.try_state::<Mutex<Option<Safe>>>();
-    safe.is_some() // state is managed
-        && safe.unwrap().lock().await.as_ref().is_some() // option<safe> is some
+async fn sign_out(app: AppHandle) -> Result<(), Error> {
+    if let Some(safe) = app.try_state::<Client>() {
+        if let Some(safe) = safe.lock().await.as_mut() {
+            // there is no logout in Safe, so replace user's wallet with a random, empty one
+            safe.login_with_eth(None)?;
+        }
+    }
+
+    app.state::<Mutex<WalletState>>().lock().await.signed_out();
+    app.state::<Mutex<Session>>().lock().await.clear(); // user and everything else
+
+    let _ = app.emit("sign_out", ()).inspect_err(|e| error!("{}", e));
+
+    Ok(())
 }
 
-#[tauri::command]
-async fn disconnect(app: AppHandle) -> Result<(), Error> {
-    app.unmanage::<Mutex<Option<Safe>>>()
-        .ok_or(Error::NotConnected)?;
+// Makes the key unusable until unlock, without signing out.
+async fn lock_wallet(app: &AppHandle) -> Result<(), Error> {
+    if let Some(safe) = app.try_state::<Client>() {
+        if let Some(safe) = safe.lock().await.as_mut() {
+            safe.login_with_eth(None)?; // same as in sign_out
+        }
+    }
+    app.state::<Mutex<WalletState>>().lock().await.mark_locked();
 
-    let _ = app
-        .emit("disconnected", ())
-        .inspect_err(|e| eprintln!("{}", e));
+    let _ = app.emit("locked", ()).inspect_err(|e| error!("{}", e));
 
     Ok(())
 }
 
+async fn lock_when_idle(app: AppHandle) {
+    loop {
+        tokio::time::sleep(LOCK_CHECK_INTERVAL).await;
+
+        let should_lock = app
+            .state::<Mutex<WalletState>>()
+            .lock()
+            .await
+            .should_lock(std::time::Instant::now());
+        if should_lock {
+            info!("Locking wallet after inactivity.");
+            let _ = lock_wallet(&app)
+                .await
+                .inspect_err(|e| error!("Could not lock wallet: {}", e));
+        }
+    }
+}
+
 #[tauri::command]
-async fn log_level(level: String, app: AppHandle) -> Result<(), Error> {
-    let _ = app
-        .try_state::<Mutex<Option<Safe>>>()
+async fn unlock(password: String, app: AppHandle) -> Result<(), Error> {
+    let login = {
+        let wallet = app.state::<Mutex<WalletState>>();
+        let wallet = wallet.lock().await;
+        if wallet.watch_only {
+            return Err(Error::WatchOnly);
+        }
+        wallet.login.clone().ok_or(Error::BadLogin)? // nobody signed in
+    };
+
+    let pk = load_create_import_key(
+        app.state::<Box<dyn KeyStore>>().as_ref(),
+        &login,
+        password,
+        None,
+        false,
+    )?;
+
+    app.try_state::<Client>()
         .ok_or(Error::NotConnected)?
         .lock()
         .await
         .as_mut()
-        .ok_or(Error::NotConnected)? // safe
-        .log_level(&level)?;
+        .ok_or(Error::NotConnected)?
+        .login_with_eth(Some(pk.clone()))?;
+
+    app.state::<Mutex<WalletState>>().lock().await.unlocked(pk);
+
+    let _ = app.emit("unlocked", ()).inspect_err(|e| error!("{}", e));
+    queue::schedule_replay(&app);
 
     Ok(())
 }
 
+#[tauri::command]
+async fn is_locked(wallet: State<'_, Mutex<WalletState>>) -> Result<bool, Error> {
+    Ok(wallet.lock().await.locked)
+}
+
+// seconds of inactivity, None to never lock
+#[tauri::command]
+async fn set_lock_timeout(
+    seconds: Option<u64>,
+    wallet: State<'_, Mutex<WalletState>>,
+) -> Result<(), Error> {
+    wallet.lock().await.lock_timeout = seconds.map(std::time::Duration::from_secs);
+    Ok(())
+}
+
+#[tauri::command]
+async fn is_connected(app: AppHandle) -> bool {
+    connection::state(&app).await == ConnectionState::Connected
+}
+
+// Waits up to timeout for network operations in progress, then aborts them.
+// Zero timeout aborts them right away.
+#[tauri::command]
+async fn disconnect(timeout_secs: Option<u64>, app: AppHandle) -> Result<(), Error> {
+    let timeout = timeout_secs
+        .map(std::time::Duration::from_secs)
+        .unwrap_or(operations::DEFAULT_DRAIN_TIMEOUT);
+    let aborted = connection::disconnect(&app, timeout).await?;
+
+    let _ = app
+        .emit("disconnected", Disconnected { aborted })
+        .inspect_err(|e| error!("{}", e));
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn log_level(level: String, app: AppHandle) -> Result<(), Error> {
+    if let Some(logging) = app.try_state::<Logging>() {
+        logging.set_level(&level)?;
+    }
+
+    // network client has its own logging, set when connected
+    if let Some(client) = app.try_state::<Client>() {
+        if let Some(client) = client.lock().await.as_mut() {
+            client.log_level(&level)?;
+        }
+    }
+
+    Ok(())
+}
+
+// zips log files to attach to a bug report
+#[tauri::command]
+async fn export_logs(destination: String, app: AppHandle) -> Result<(), Error> {
+    app.try_state::<Logging>()
+        .ok_or(Error::Common(String::from("Logging to file is not set up.")))?
+        .archive(&PathBuf::from(destination))
+}
+
 type Session = std::collections::HashMap<String, String>;
 
 #[tauri::command]
@@ -338,30 +1018,123 @@ fn meta_builder(name: Vec<String>) -> Result<XorNameBuilder, Error> {
     Ok(mb)
 }
 
+// Signed in user did something, postpones locking the wallet.
+async fn touch_wallet(app: &AppHandle) {
+    app.state::<Mutex<WalletState>>().lock().await.touch();
+}
+
+// key for private registers of the signed in account
+async fn register_key(wallet: &Mutex<WalletState>) -> Result<RegisterKey, Error> {
+    let wallet = wallet.lock().await;
+    if wallet.watch_only {
+        return Err(Error::WatchOnly);
+    }
+    match wallet.eth_pk() {
+        Some(eth_pk) => RegisterKey::new(&eth_pk),
+        None if wallet.locked => Err(Error::Locked),
+        None => Err(Error::Common(String::from("Sign in to use private registers."))),
+    }
+}
+
+// Register bytes to write, encrypted for the signed in account if private.
+// Payload too big for a register is uploaded first.
+async fn seal_register(
+    envelope: Envelope,
+    meta: &XorName,
+    private: bool,
+    safe: &Client,
+    wallet: &Mutex<WalletState>,
+    app: &AppHandle,
+) -> Result<Vec<u8>, Error> {
+    let envelope = if private {
+        envelope.seal(&register_key(wallet).await?, meta)?
+    } else {
+        envelope
+    };
+
+    let Some(payload) = envelope.oversized_payload()? else {
+        return envelope.to_bytes();
+    };
+    let xorname = operations::run(app, Operation::Upload, async {
+        Ok(safe.lock()
+            .await
+            .as_mut()
+            .ok_or(Error::NotConnected)?
+            .upload(&payload)
+            .await?)
+    })
+    .await?;
+    debug!(xorname = %xorname, size = payload.len(), "Register payload stored as data.");
+    envelope.point_to(xorname, &payload).to_bytes()
+}
+
+// Register value, following pointer to its payload, private ones decrypted
+// with the signed in account's key.
+async fn open_register(
+    bytes: &[u8],
+    meta: &XorName,
+    schema: Schema,
+    safe: &Client,
+    wallet: &Mutex<WalletState>,
+    app: &AppHandle,
+) -> Result<Envelope, Error> {
+    let mut envelope = Envelope::from_bytes(bytes, schema)?;
+
+    if let Some(pointer) = &envelope.pointer {
+        let xorname = parse_xorname(&pointer.xorname)?;
+        let payload = operations::run(app, Operation::Download, async {
+            Ok(safe.lock()
+                .await
+                .as_mut()
+                .ok_or(Error::NotConnected)?
+                .download(xorname)
+                .await?)
+        })
+        .await?;
+        envelope = envelope.resolve(&payload)?;
+    }
+
+    if !envelope.encrypted {
+        return Ok(envelope);
+    }
+    envelope.open(&register_key(wallet).await?, meta)
+}
+
 #[tauri::command]
 async fn create_reg(
     name: Vec<String>,
-    data: String,
-    safe: State<'_, Mutex<Option<Safe>>>,
+    schema: Schema,
+    data: String,  // JSON payload, empty for initial value of the schema
+    private: bool, // encrypted, readable only by this account
+    safe: State<'_, Client>,
+    wallet: State<'_, Mutex<WalletState>>,
+    app: AppHandle,
 ) -> Result<(), Error> {
-    println!("\n\nReg create...");
-    println!("Name: {:?}", name);
+    wallet.lock().await.ensure_can_pay()?;
+
+    debug!(?name, "Creating register...");
 
     let meta = meta_builder(name)
         .unwrap_or(XorNameBuilder::random())
         .build();
-    println!("Data: {}", &data);
-    println!("Meta: {}", &meta);
+    // not the data itself, private one is secret
+    debug!(meta = %meta, size = data.len(), private, "Register data");
 
     //    let (mut reg, cost, royalties) = safe
-    safe.lock()
-        .await
-        .as_mut()
-        .ok_or(Error::NotConnected)?
-        .reg_create(data.as_bytes(), &meta)
-        .await?;
+    let writer = safe.lock().await.as_mut().ok_or(Error::NotConnected)?.address()?;
+    let envelope = Envelope::new(schema, &data, writer)?;
+    let bytes = seal_register(envelope, &meta, private, &safe, &wallet, &app).await?;
+    operations::run(&app, Operation::RegCreate, async {
+        Ok(safe.lock()
+            .await
+            .as_mut()
+            .ok_or(Error::NotConnected)?
+            .reg_create(&bytes, &meta)
+            .await?)
+    })
+    .await?;
 
-    println!("\n\nReg created");
+    info!(meta = %meta, "Register created.");
     //    println!("Costs: {}, {}", cost, royalties);
 
     //    Ok((reg.address().to_hex(), cost.as_nano(), royalties.as_nano()))
@@ -371,43 +1144,84 @@ async fn create_reg(
 #[tauri::command]
 async fn read_reg(
     name: Vec<String>,
-    safe: State<'_, Mutex<Option<Safe>>>,
-) -> Result<String, Error> {
+    schema: Schema,
+    safe: State<'_, Client>,
+    wallet: State<'_, Mutex<WalletState>>,
+    app: AppHandle,
+) -> Result<Envelope, Error> {
+    touch_wallet(&app).await;
     let meta = meta_builder(name)?.build();
+    let owner = wallet.lock().await.watched.clone();
 
-    let data = safe
-        .lock()
-        .await
-        .as_mut()
-        .ok_or(Error::NotConnected)?
-        .read_reg(&meta, None)
-        .await?;
+    let data = operations::run(&app, Operation::ReadReg, async {
+        Ok(safe.lock()
+            .await
+            .as_mut()
+            .ok_or(Error::NotConnected)?
+            .read_reg(&meta, owner.as_deref())
+            .await?)
+    })
+    .await?;
 
-    Ok(String::from_utf8(data).map_err(|e| Error::Common(format!("{e}")))?)
+    open_register(&data, &meta, schema, &safe, &wallet, &app).await
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)] // tauri injects the state ones
 async fn write_reg(
     name: Vec<String>,
-    data: String,
-    safe: State<'_, Mutex<Option<Safe>>>,
+    schema: Schema,
+    data: String,                  // JSON payload
+    private: bool,                 // encrypted, readable only by this account
+    expected_revision: Option<u32>, // revision it is based on, None overwrites anything
+    safe: State<'_, Client>,
+    wallet: State<'_, Mutex<WalletState>>,
+    app: AppHandle,
 ) -> Result<(), Error> {
-    println!("\n\nReg write...");
-    println!("Name: {:?}", name);
+    wallet.lock().await.ensure_can_pay()?;
+
+    debug!(?name, "Writing register...");
 
     let meta = meta_builder(name)?.build();
-    println!("Meta: {}", meta);
+    // not the data itself, private one is secret
+    debug!(meta = %meta, size = data.len(), private, "Register data");
 
-    println!("Writing data: {}", &data);
     if !data.is_empty() {
-        safe.lock()
-            .await
-            .as_mut()
-            .ok_or(Error::NotConnected)?
-            .reg_write(data.as_bytes(), &meta)
-            .await?;
+        // Not atomic: a write landing after this read, also while an oversized payload
+        // is uploaded by seal_register, is not detected and gets the same revision.
+        let head = operations::run(&app, Operation::ReadReg, async {
+            Ok(safe.lock()
+                .await
+                .as_mut()
+                .ok_or(Error::NotConnected)?
+                .read_reg(&meta, None)
+                .await?)
+        })
+        .await?;
+        let revision = registers::revision(&head);
+        if expected_revision.is_some_and(|expected| expected != revision) {
+            let current = open_register(&head, &meta, schema, &safe, &wallet, &app).await?;
+            return Err(Error::Conflict {
+                revision,
+                current: Box::new(current),
+            });
+        }
+
+        let writer = safe.lock().await.as_mut().ok_or(Error::NotConnected)?.address()?;
+        let mut envelope = Envelope::new(schema, &data, writer)?;
+        envelope.revision = revision + 1;
+        let bytes = seal_register(envelope, &meta, private, &safe, &wallet, &app).await?;
+        operations::run(&app, Operation::RegWrite, async {
+            Ok(safe.lock()
+                .await
+                .as_mut()
+                .ok_or(Error::NotConnected)?
+                .reg_write(&bytes, &meta)
+                .await?)
+        })
+        .await?;
 
-        println!("\n\nReg updated.");
+        info!(meta = %meta, revision = revision + 1, "Register updated.");
     } else {
         return Err(Error::Common(String::from("Empty data object string.")));
     }
@@ -415,8 +1229,116 @@ async fn write_reg(
     Ok(())
 }
 
+// Three-way merge of register documents, None if changes conflict.
+#[tauri::command]
+fn merge_documents(
+    schema: Schema,
+    base: String, // value both sides started from
+    ours: String,
+    theirs: String,
+) -> Result<Option<serde_json::Value>, Error> {
+    let base = registers::parse_payload(schema, &base)?;
+    let ours = registers::parse_payload(schema, &ours)?;
+    let theirs = registers::parse_payload(schema, &theirs)?;
+
+    let Some(merged) = merge::merge(&base, &ours, &theirs) else {
+        return Ok(None);
+    };
+    schema.validate(&merged)?;
+    Ok(Some(merged))
+}
+
 #[tauri::command]
-async fn client_address(safe: State<'_, Mutex<Option<Safe>>>) -> Result<String, Error> {
+async fn reg_history(
+    name: Vec<String>,
+    safe: State<'_, Client>,
+    wallet: State<'_, Mutex<WalletState>>,
+    app: AppHandle,
+) -> Result<RegisterHistory, Error> {
+    touch_wallet(&app).await;
+    let meta = meta_builder(name)?.build();
+    let owner = wallet.lock().await.watched.clone();
+
+    let (history, complete) = operations::run(&app, Operation::ReadReg, async {
+        Ok(safe.lock()
+            .await
+            .as_mut()
+            .ok_or(Error::NotConnected)?
+            .reg_history(&meta, owner.as_deref())
+            .await?)
+    })
+    .await?;
+    if !complete {
+        warn!(meta = %meta, versions = history.len(), "Register history is cut short.");
+    }
+
+    Ok(RegisterHistory {
+        versions: history
+            .iter()
+            .zip(0..)
+            .map(|(bytes, version)| RegisterVersion::new(version, bytes))
+            .collect(),
+        complete,
+    })
+}
+
+#[tauri::command]
+async fn read_reg_version(
+    name: Vec<String>,
+    schema: Schema,
+    version: u32,
+    safe: State<'_, Client>,
+    wallet: State<'_, Mutex<WalletState>>,
+    app: AppHandle,
+) -> Result<Envelope, Error> {
+    touch_wallet(&app).await;
+    let meta = meta_builder(name)?.build();
+    let owner = wallet.lock().await.watched.clone();
+
+    let data = operations::run(&app, Operation::ReadReg, async {
+        Ok(safe.lock()
+            .await
+            .as_mut()
+            .ok_or(Error::NotConnected)?
+            .read_reg_version(&meta, version, owner.as_deref())
+            .await?)
+    })
+    .await?;
+
+    open_register(&data, &meta, schema, &safe, &wallet, &app).await
+}
+
+// Writes a past value back as the newest one, history stays as it was. Private stays private.
+#[tauri::command]
+async fn restore_reg_version(
+    name: Vec<String>,
+    schema: Schema,
+    version: u32,
+    safe: State<'_, Client>,
+    wallet: State<'_, Mutex<WalletState>>,
+    app: AppHandle,
+) -> Result<(), Error> {
+    wallet.lock().await.ensure_can_pay()?;
+
+    let old = read_reg_version(
+        name.clone(),
+        schema,
+        version,
+        safe.clone(),
+        wallet.clone(),
+        app.clone(),
+    )
+    .await?;
+    let data = serde_json::to_string(&old.payload)
+        .map_err(|e| Error::Common(format!("Could not serialize register: {}", e)))?;
+
+    info!(?name, version, "Restoring register version.");
+    write_reg(name, schema, data, old.encrypted, None, safe, wallet, app).await
+}
+
+#[tauri::command]
+async fn client_address(safe: State<'_, Client>, app: AppHandle) -> Result<String, Error> {
+    touch_wallet(&app).await;
     let address = safe
         .lock()
         .await
@@ -427,51 +1349,105 @@ async fn client_address(safe: State<'_, Mutex<Option<Safe>>>) -> Result<String,
 }
 
 #[tauri::command]
-async fn balance(safe: State<'_, Mutex<Option<Safe>>>) -> Result<String, Error> {
-    let balance = safe
-        .lock()
-        .await
-        .as_mut()
-        .ok_or(Error::NotConnected)?
-        .balance()
-        .await?;
+async fn balance(safe: State<'_, Client>, app: AppHandle) -> Result<String, Error> {
+    touch_wallet(&app).await;
+    let balance = operations::run(&app, Operation::Balance, async {
+        Ok(safe.lock()
+            .await
+            .as_mut()
+            .ok_or(Error::NotConnected)?
+            .balance()
+            .await?)
+    })
+    .await?;
     //    Ok(format!("{:x}", balance)) // hex string
     Ok(format!("{}", balance.0))
 }
 
 #[tauri::command]
-async fn gas_balance(safe: State<'_, Mutex<Option<Safe>>>) -> Result<String, Error> {
-    let balance = safe
-        .lock()
-        .await
-        .as_mut()
-        .ok_or(Error::NotConnected)?
-        .balance()
-        .await?;
+async fn gas_balance(safe: State<'_, Client>, app: AppHandle) -> Result<String, Error> {
+    touch_wallet(&app).await;
+    let balance = operations::run(&app, Operation::Balance, async {
+        Ok(safe.lock()
+            .await
+            .as_mut()
+            .ok_or(Error::NotConnected)?
+            .balance()
+            .await?)
+    })
+    .await?;
     //    Ok(format!("{:x}", balance)) // hex string
     Ok(format!("{}", balance.1))
 }
 
 #[tauri::command]
-fn check_key(login: String, password: String, mut app: AppHandle) -> Result<String, Error> {
-    let app_root = make_root(&mut app)?;
-    load_create_import_key(&app_root, login, password, None, false)
+fn check_key(
+    login: String,
+    password: String,
+    key_store: State<'_, Box<dyn KeyStore>>,
+) -> Result<String, Error> {
+    load_create_import_key(
+        key_store.as_ref(),
+        &AccountName::parse(&login)?,
+        password,
+        None,
+        false,
+    )
 }
 
+// Account is moved to trash, and can be restored for TRASH_RETENTION_SECS.
 #[tauri::command]
-fn delete_account(login: String, mut app: AppHandle) -> Result<(), Error> {
-    let app_root = make_root(&mut app)?;
-    let sk_dir = user_root(&app_root, login);
-    if sk_dir.try_exists().map_err(|_| {
-        Error::Common(format!(
-            "Could not check existence of {}.",
-            sk_dir.display()
-        ))
-    })? {
-        fs::remove_dir_all(&sk_dir)
-            .map_err(|e| Error::Common(format!("Could not remove {}: {}", sk_dir.display(), e)))?
+fn delete_account(
+    login: String,
+    password: Option<String>, // not needed for watch-only accounts
+    key_store: State<'_, Box<dyn KeyStore>>,
+    mut app: AppHandle,
+) -> Result<TrashEntry, Error> {
+    let login = AccountName::parse(&login)?;
+    let account_dir = user_root(&make_root(&mut app)?, &login);
+
+    if !account_dir.join(WATCH_ONLY_FILENAME).exists() {
+        load_create_import_key(
+            key_store.as_ref(),
+            &login,
+            password.ok_or(Error::BadPassword)?,
+            None,
+            false,
+        )?;
     }
-    Ok(())
+
+    key_store.trash(&login)
+}
+
+#[tauri::command]
+fn list_deleted_accounts(
+    key_store: State<'_, Box<dyn KeyStore>>,
+) -> Result<Vec<TrashEntry>, Error> {
+    key_store.purge_trash(profile::now().saturating_sub(TRASH_RETENTION_SECS))?;
+
+    let mut entries = key_store.list_trash()?;
+    entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at)); // most recent first
+    Ok(entries)
+}
+
+#[tauri::command]
+fn restore_deleted_account(
+    id: String,
+    login: Option<String>, // restore under a different name, if the old one is taken
+    key_store: State<'_, Box<dyn KeyStore>>,
+) -> Result<AccountName, Error> {
+    let entry = key_store
+        .list_trash()?
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .ok_or(Error::Common(format!("Unknown deleted account: {}", id)))?;
+
+    let login = match login {
+        Some(login) => AccountName::parse(&login)?,
+        None => entry.login,
+    };
+    key_store.restore(&id, &login)?;
+    Ok(login)
 }
 
 fn truncate_to_max_length(value: String, max_length: usize) -> String {
@@ -630,7 +1606,7 @@ async fn save_file_metadata(song_file: FileMetadata, app: AppHandle) -> Result<(
     let full_path = String::from(song_file.full_path()?.to_string_lossy().as_ref());
 
     let mut tagged_file = read_from_path(&full_path)
-        .map_err(|e| Error::Common(format!("Cannot read tags from file {}", full_path)))?;
+        .map_err(|_| Error::TagRead(full_path.clone()))?;
     tagged_file.clear();
     let mut new_tag = Tag::new(tagged_file.primary_tag_type());
 
@@ -672,7 +1648,7 @@ async fn save_file_metadata(song_file: FileMetadata, app: AppHandle) -> Result<(
 
     new_tag
         .save_to_path(&full_path, WriteOptions::default())
-        .map_err(|e| Error::Common(format!("Cannot save tags to file {}", full_path)))?;
+        .map_err(|_| Error::TagWrite(full_path.clone()))?;
     Ok(())
 }
 
@@ -683,26 +1659,25 @@ async fn download(
     destination: String,       // directory to download to
     app: AppHandle,
 ) -> Result<FileMetadata, Error> {
-    let xorname_bytes: [u8; 32] = hex::decode(xorname)
-        .map_err(|e| Error::Common(format!("Invalid xorname: {}", e)))?[0..32]
-        .try_into()
-        .unwrap();
-    let xorname = XorName(xorname_bytes);
+    let xorname = parse_xorname(&xorname)?;
+    touch_wallet(&app).await;
 
-    let data = app
-        .try_state::<Mutex<Option<Safe>>>()
-        .ok_or(Error::NotConnected)?
-        .lock()
-        .await
-        .as_mut()
-        .ok_or(Error::NotConnected)?
-        .download(xorname)
-        .await?;
+    let data = operations::run(&app, Operation::Download, async {
+        Ok(app.try_state::<Client>()
+            .ok_or(Error::NotConnected)?
+            .lock()
+            .await
+            .as_mut()
+            .ok_or(Error::NotConnected)?
+            .download(xorname)
+            .await?)
+    })
+    .await?;
 
     let size = data.len();
     let mut reader = std::io::Cursor::new(data);
     let tagged_file = TaggedFile::read_from(&mut reader, ParseOptions::default())
-        .map_err(|e| Error::Common(format!("Cannot read file data for tagging : {}", e)))?;
+        .map_err(|_| Error::TagRead(hex::encode(xorname)))?;
     let data = reader.into_inner(); // get ownership back, avoid cloning data
 
     let mut metadata = FileMetadata::from_tagged_file(&tagged_file);
@@ -772,8 +1747,7 @@ async fn download(
         path.push(filename_parts.join(""));
     }
 
-    fs::write(&path, data)
-        .map_err(|_| Error::Common(format!("Could not save song: {}", path.display())))?;
+    fs::write(&path, data).map_err(|e| Error::file_io(&path, e))?;
 
     Ok(metadata)
 }
@@ -784,28 +1758,125 @@ async fn upload(
     file: String, // file path
     app: AppHandle,
 ) -> Result<String, Error> {
+    // checked before reading a possibly big file, put_data checks again
+    app.state::<Mutex<WalletState>>()
+        .lock()
+        .await
+        .ensure_can_pay()?;
+
     let path = PathBuf::from(file);
     let data = fs::read(&path)
-        .map_err(|e| Error::Common(format!("File {} is not readable: {}", path.display(), e)))?;
+        .map_err(|e| Error::file_io(&path, e))?;
     put_data(data, app).await
 }
 
 // returns hex-encoded xorname
 #[tauri::command]
 async fn put_data(data: Vec<u8>, app: AppHandle) -> Result<String, Error> {
-    let data_address = app
-        .try_state::<Mutex<Option<Safe>>>()
-        .ok_or(Error::NotConnected)?
+    app.state::<Mutex<WalletState>>()
         .lock()
         .await
-        .as_mut()
-        .ok_or(Error::NotConnected)? // safe
-        .upload(&data)
-        .await?;
+        .ensure_can_pay()?;
+
+    let data_address = operations::run(&app, Operation::Upload, async {
+        Ok(app.try_state::<Client>()
+            .ok_or(Error::NotConnected)?
+            .lock()
+            .await
+            .as_mut()
+            .ok_or(Error::NotConnected)?
+            .upload(&data)
+            .await?)
+    })
+    .await?;
 
     Ok(hex::encode(data_address))
 }
 
+// Postpones a network write until connected, it is replayed right away when already connected.
+async fn enqueue(app: &AppHandle, op: QueuedOp, payload: Option<&[u8]>) -> Result<QueueItem, Error> {
+    let login = {
+        let wallet = app.state::<Mutex<WalletState>>();
+        let mut wallet = wallet.lock().await;
+        if wallet.watch_only {
+            return Err(Error::WatchOnly);
+        }
+        wallet.touch();
+        wallet.login.clone().ok_or(Error::BadLogin)? // not signed in
+    };
+
+    let item = app
+        .state::<Mutex<OfflineQueue>>()
+        .lock()
+        .await
+        .push(&login, op, payload)?;
+
+    if connection::state(app).await == ConnectionState::Connected {
+        queue::schedule_replay(app);
+    }
+    Ok(item)
+}
+
+#[tauri::command]
+async fn queue_upload(file: String, app: AppHandle) -> Result<QueueItem, Error> {
+    let path = PathBuf::from(&file);
+    if !path.is_file() {
+        return Err(Error::FileIo {
+            path: path.display().to_string(),
+            reason: String::from("File does not exist."),
+        });
+    }
+    enqueue(&app, QueuedOp::Upload { file }, None).await
+}
+
+#[tauri::command]
+async fn queue_put_data(data: Vec<u8>, app: AppHandle) -> Result<QueueItem, Error> {
+    enqueue(&app, QueuedOp::PutData { size: data.len() }, Some(&data)).await
+}
+
+#[tauri::command]
+async fn queue_write_reg(
+    name: Vec<String>,
+    schema: Schema,
+    data: String,
+    private: bool,
+    app: AppHandle,
+) -> Result<QueueItem, Error> {
+    if data.is_empty() {
+        return Err(Error::Common(String::from("Empty data object string.")));
+    }
+    // validate now, not during replay
+    meta_builder(name.clone())?;
+    registers::parse_payload(schema, &data)?;
+    let data = if private {
+        queue::seal_data(&data, &register_key(&app.state::<Mutex<WalletState>>()).await?)?
+    } else {
+        data
+    };
+    let op = QueuedOp::WriteReg {
+        name,
+        schema,
+        data,
+        private,
+    };
+    enqueue(&app, op, None).await
+}
+
+// oldest first, in the order they get replayed
+#[tauri::command]
+async fn list_queue(queue: State<'_, Mutex<OfflineQueue>>) -> Result<Vec<QueueItem>, Error> {
+    Ok(queue.lock().await.list())
+}
+
+#[tauri::command]
+async fn cancel_queued(id: u64, queue: State<'_, Mutex<OfflineQueue>>) -> Result<QueueItem, Error> {
+    queue
+        .lock()
+        .await
+        .remove(id)?
+        .ok_or(Error::Common(format!("No queued item {}.", id)))
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -816,10 +1887,31 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_os::init())
         .manage(Mutex::new(Session::new()))
+        .manage(Client::default())
+        .manage(HealthCheck::default())
+        .manage(Mutex::new(Diagnostics::default()))
+        .manage(Mutex::new(InFlight::default()))
+        .manage(Mutex::new(WalletState::default()))
         .invoke_handler(tauri::generate_handler![
             list_accounts,
+            update_account_profile,
             connect,
+            cancel_connect,
+            set_connect_timeout,
+            network_setting,
+            set_network,
+            network_diagnostics,
+            export_diagnostics,
+            export_logs,
+            connection_status,
             sign_in,
+            recover_account,
+            sign_out,
+            add_watch_only_account,
+            sign_in_watch_only,
+            unlock,
+            is_locked,
+            set_lock_timeout,
             is_connected,
             disconnect,
             log_level,
@@ -828,18 +1920,74 @@ pub fn run() {
             create_reg,
             read_reg,
             write_reg,
+            reg_history,
+            read_reg_version,
+            restore_reg_version,
+            merge_documents,
             client_address,
             balance,
             gas_balance,
             check_key,
+            change_password,
+            export_account,
+            import_account,
             delete_account,
+            list_deleted_accounts,
+            restore_deleted_account,
             get_file_metadata,
             save_file_metadata,
             download,
             upload,
             put_data,
+            queue_upload,
+            queue_put_data,
+            queue_write_reg,
+            list_queue,
+            cancel_queued,
         ])
         .setup(|app| {
+            let app_root = make_root(&mut app.handle().clone())?;
+            // app works without logs, so don't fail on them
+            match logging::init(app.handle().clone(), &app_root, DEFAULT_LOG_LEVEL) {
+                Ok(logging) => {
+                    app.manage(logging);
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+            let key_store =
+                FileKeyStore::new(app_root.join(ACCOUNTS_DIR), app_root.join(TRASH_DIR));
+            // a broken account directory must not keep the app from starting
+            let _ = key_store
+                .migrate_legacy_names()
+                .inspect_err(|e| error!("Could not migrate accounts: {}", e));
+            let _ = key_store
+                .purge_trash(profile::now().saturating_sub(TRASH_RETENTION_SECS))
+                .inspect_err(|e| warn!("Could not purge deleted accounts: {}", e));
+            let logins = key_store
+                .list()
+                .inspect_err(|e| error!("Could not list accounts: {}", e))
+                .unwrap_or_default();
+            for login in logins {
+                let account_dir = key_store.account_dir(&login);
+                if let Ok(None) = AccountProfile::load(&account_dir) {
+                    let _ = AccountProfile::from_legacy(login.to_string(), &account_dir)
+                        .save(&account_dir)
+                        .inspect_err(|e| error!("{}", e));
+                }
+            }
+            app.manage::<Box<dyn KeyStore>>(Box::new(key_store));
+
+            let network = NetworkSetting::load(&app_root)
+                .inspect_err(|e| error!("{}", e))
+                .ok()
+                .flatten()
+                .unwrap_or_default();
+            app.manage(Mutex::new(Connection::new(network)));
+            app.manage(Mutex::new(OfflineQueue::open(app_root.join(QUEUE_DIR))?));
+
+            tauri::async_runtime::spawn(lock_when_idle(app.handle().clone()));
+            tauri::async_runtime::spawn(connection::supervise(app.handle().clone()));
+
 			#[cfg(target_os = "linux")]
 			server::run(app.handle().clone());
 
@@ -848,3 +1996,102 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keystore::MemoryKeyStore;
+
+    #[test]
+    fn register_and_load_key() {
+        let keys = MemoryKeyStore::default();
+        let alice = AccountName::parse("alice").unwrap();
+        let pk = String::from("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+
+        assert!(matches!(
+            load_create_import_key(&keys, &alice, "pass".into(), None, false),
+            Err(Error::BadLogin)
+        ));
+        assert_eq!(
+            pk,
+            load_create_import_key(&keys, &alice, "pass".into(), Some(pk.clone()), true)
+                .unwrap()
+        );
+        assert!(
+            load_create_import_key(&keys, &alice, "pass".into(), None, true).is_err()
+        );
+        assert_eq!(
+            pk,
+            load_create_import_key(&keys, &alice, "pass".into(), None, false).unwrap()
+        );
+        assert!(matches!(
+            load_create_import_key(&keys, &alice, "wrong".into(), None, false),
+            Err(Error::BadPassword)
+        ));
+    }
+
+    #[test]
+    fn change_password_flow() {
+        let keys = MemoryKeyStore::default();
+        let alice = AccountName::parse("alice").unwrap();
+        let pk = load_create_import_key(&keys, &alice, "old".into(), None, true).unwrap();
+
+        assert!(matches!(
+            change_key_password(&keys, &alice, "bad", "new"),
+            Err(Error::BadPassword)
+        ));
+        assert!(matches!(
+            change_key_password(&keys, &AccountName::parse("bob").unwrap(), "old", "new"),
+            Err(Error::BadLogin)
+        ));
+        change_key_password(&keys, &alice, "old", "new").unwrap();
+
+        assert!(
+            load_create_import_key(&keys, &alice, "old".into(), None, false).is_err()
+        );
+        assert_eq!(
+            pk,
+            load_create_import_key(&keys, &alice, "new".into(), None, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn error_codes() {
+        let json = serde_json::to_value(Error::FileIo {
+            path: "song.mp3".into(),
+            reason: "denied".into(),
+        })
+        .unwrap();
+        assert_eq!("FileIo", json["code"]);
+        assert_eq!("File song.mp3: denied", json["message"]);
+        assert_eq!("song.mp3", json["details"]["path"]);
+
+        let json = serde_json::to_value(Error::NotConnected).unwrap();
+        assert_eq!("NotConnected", json["code"]);
+        assert!(json.get("details").is_none());
+
+        let json = serde_json::to_value(Error::AccountExists("alice".into())).unwrap();
+        assert_eq!("AccountExists", json["code"]);
+        assert_eq!("alice", json["details"]["login"]);
+        let json = serde_json::to_value(Error::AlreadyImported("alice".into())).unwrap();
+        assert_eq!("AlreadyImported", json["code"]);
+
+        let code = |e: safeapi::Error| Error::from(e).code();
+        assert_eq!("NotConnected", code(safeapi::Error::NotConnected));
+        assert_eq!("BadPassword", code(safeapi::Error::BadPassword));
+        let custom = |message: &str| code(safeapi::Error::Custom(message.into()));
+        assert_eq!("InsufficientFunds", custom("Error occurred during payment."));
+        assert_eq!("InsufficientGas", custom("insufficient funds for gas * price + value"));
+        assert_eq!("NotFound", custom("get: not found"));
+        assert_eq!("Timeout", custom("Operation timed out"));
+        assert_eq!("Network", custom("Connection refused"));
+    }
+
+    #[test]
+    fn xorname_parsing() {
+        let hex = "08dbb205f5a5712e48551c0e437f07be304a5daadf20e07e8307e7f564fa9962";
+        assert_eq!(hex, hex::encode(parse_xorname(hex).unwrap()));
+        assert!(matches!(parse_xorname("08dbb2"), Err(Error::InvalidAddress(_))));
+        assert!(matches!(parse_xorname("not hex"), Err(Error::InvalidAddress(_))));
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{Error, PathBuf, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use tauri::{AppHandle, Emitter};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_appender::rolling::{Builder, Rotation};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const LOG_DIR: &str = "logs";
+const LOG_FILE_PREFIX: &str = "jams";
+const LOG_FILE_SUFFIX: &str = "log";
+const MAX_LOG_FILES: usize = 7; // one per day
+const CRATE: &str = env!("CARGO_CRATE_NAME");
+
+// payload of "backend_log" event
+#[derive(Serialize, Clone)]
+pub(crate) struct BackendLog {
+    level: String,
+    target: String,
+    message: String,
+}
+
+// Runtime control of logging, managed by the app.
+pub(crate) struct Logging {
+    filter: reload::Handle<EnvFilter, Registry>,
+    dir: PathBuf,
+}
+
+// Level applies to our code, dependencies log only warnings and errors.
+fn filter(level: &str) -> Result<EnvFilter, Error> {
+    let level = level
+        .parse::<tracing::Level>()
+        .map_err(|_| Error::Common(format!("Invalid log level {}.", level)))?;
+    Ok(EnvFilter::new(format!("warn,{}={}", CRATE, level)))
+}
+
+// Logs to stdout, to a daily rotated file in app data dir, and to frontend.
+pub(crate) fn init(app: AppHandle, app_root: &PathBuf, level: &str) -> Result<Logging, Error> {
+    let dir = app_root.join(LOG_DIR);
+    let file = Builder::new()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| Error::Common(format!("Could not open log file in {}: {}", dir.display(), e)))?;
+
+    let (filter, handle) = reload::Layer::new(filter(level)?);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .with(fmt::layer().with_ansi(false).with_writer(file))
+        .with(FrontendLayer { app })
+        .try_init()
+        .map_err(|e| Error::Common(format!("Could not set up logging: {}", e)))?;
+
+    Ok(Logging { filter: handle, dir })
+}
+
+impl Logging {
+    pub(crate) fn set_level(&self, level: &str) -> Result<(), Error> {
+        self.filter
+            .reload(filter(level)?)
+            .map_err(|e| Error::Common(format!("Could not change log level: {}", e)))
+    }
+
+    // Zips log files to destination, for attaching to bug reports.
+    pub(crate) fn archive(&self, destination: &PathBuf) -> Result<(), Error> {
+        let io_error = |e: std::io::Error| {
+            Error::Common(format!("Could not archive logs to {}: {}", destination.display(), e))
+        };
+        let zip_error = |e: zip::result::ZipError| {
+            Error::Common(format!("Could not archive logs to {}: {}", destination.display(), e))
+        };
+
+        let mut log_files = fs::read_dir(&self.dir)
+            .map_err(io_error)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with(LOG_FILE_PREFIX))
+            })
+            .collect::<Vec<PathBuf>>();
+        log_files.sort();
+
+        let mut zip = ZipWriter::new(fs::File::create(destination).map_err(io_error)?);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for path in log_files {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            zip.start_file(name, options).map_err(zip_error)?;
+            zip.write_all(&fs::read(&path).map_err(io_error)?)
+                .map_err(io_error)?;
+        }
+        zip.finish().map_err(zip_error)?;
+
+        Ok(())
+    }
+}
+
+// Streams our own log events to frontend console logger.
+struct FrontendLayer {
+    app: AppHandle,
+}
+
+impl<S: Subscriber> Layer<S> for FrontendLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        // dependencies would flood it, and tauri could log about emitting this very event
+        let metadata = event.metadata();
+        if !metadata.target().starts_with(CRATE) {
+            return;
+        }
+
+        let mut message = MessageVisitor::default();
+        event.record(&mut message);
+
+        let _ = self.app.emit(
+            "backend_log",
+            BackendLog {
+                level: metadata.level().to_string(),
+                target: metadata.target().to_string(),
+                message: message.0,
+            },
+        );
+    }
+}
+
+// message first, then other fields as name=value
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.0.insert_str(0, &format!("{:?}", value));
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels() {
+        assert!(filter("INFO").is_ok());
+        assert!(filter("trace").is_ok());
+        assert!(filter("ERROR").is_ok());
+        assert!(filter("LOUD").is_err());
+    }
+}
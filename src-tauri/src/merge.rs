@@ -0,0 +1,145 @@
+use serde_json::{Map, Value};
+
+// Both sides changed the same thing differently.
+#[derive(Debug, PartialEq)]
+struct Conflict;
+
+// Three-way merge of JSON documents, None when changes conflict. Objects are merged by key,
+// arrays of objects with "id" (playlists, songs) by id, anything else only if one side kept it.
+pub(crate) fn merge(base: &Value, ours: &Value, theirs: &Value) -> Option<Value> {
+    match merge_values(Some(base), Some(ours), Some(theirs)) {
+        Ok(merged) => Some(merged.unwrap_or(Value::Null)),
+        Err(Conflict) => None,
+    }
+}
+
+// None is a missing key or array item
+fn merge_values(
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+) -> Result<Option<Value>, Conflict> {
+    if ours == theirs || theirs == base {
+        return Ok(ours.cloned());
+    }
+    if ours == base {
+        return Ok(theirs.cloned());
+    }
+
+    match (ours, theirs) {
+        (Some(Value::Object(ours)), Some(Value::Object(theirs))) => {
+            let base = base.and_then(Value::as_object);
+            Ok(Some(Value::Object(merge_objects(base, ours, theirs)?)))
+        }
+        (Some(Value::Array(ours)), Some(Value::Array(theirs)))
+            if has_ids(ours) && has_ids(theirs) =>
+        {
+            let base: &[Value] = match base {
+                Some(Value::Array(base)) if has_ids(base) => base,
+                None => &[],
+                _ => return Err(Conflict),
+            };
+            Ok(Some(Value::Array(merge_by_id(base, ours, theirs)?)))
+        }
+        _ => Err(Conflict),
+    }
+}
+
+fn merge_objects(
+    base: Option<&Map<String, Value>>,
+    ours: &Map<String, Value>,
+    theirs: &Map<String, Value>,
+) -> Result<Map<String, Value>, Conflict> {
+    let mut merged = Map::new();
+    for key in ours.keys().chain(theirs.keys().filter(|key| !ours.contains_key(*key))) {
+        let value = merge_values(
+            base.and_then(|base| base.get(key)),
+            ours.get(key),
+            theirs.get(key),
+        )?;
+        if let Some(value) = value {
+            merged.insert(key.clone(), value);
+        }
+    }
+    Ok(merged)
+}
+
+fn has_ids(items: &[Value]) -> bool {
+    items.iter().all(|item| item.get("id").is_some_and(Value::is_string))
+}
+
+// Keeps our order, items added by them go to the end.
+fn merge_by_id(base: &[Value], ours: &[Value], theirs: &[Value]) -> Result<Vec<Value>, Conflict> {
+    let find = |items: &[Value], id: &Value| items.iter().position(|item| item["id"] == *id);
+    let get = |items: &[Value], id: &Value| find(items, id).map(|i| items[i].clone());
+
+    let mut merged = vec![];
+    for item in ours {
+        let id = &item["id"];
+        let base_item = get(base, id);
+        let their_item = get(theirs, id);
+        if let Some(value) = merge_values(base_item.as_ref(), Some(item), their_item.as_ref())? {
+            merged.push(value);
+        }
+    }
+    for item in theirs.iter().filter(|item| find(ours, &item["id"]).is_none()) {
+        let base_item = get(base, &item["id"]);
+        if let Some(value) = merge_values(base_item.as_ref(), None, Some(item))? {
+            merged.push(value);
+        }
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn objects() {
+        let base = json!({"title": "Rock", "description": "loud"});
+        let ours = json!({"title": "Rock!", "description": "loud"});
+        let theirs = json!({"title": "Rock", "description": "very loud", "tags": ["live"]});
+        assert_eq!(
+            Some(json!({"title": "Rock!", "description": "very loud", "tags": ["live"]})),
+            merge(&base, &ours, &theirs)
+        );
+
+        let theirs = json!({"title": "Pop", "description": "loud"});
+        assert_eq!(None, merge(&base, &ours, &theirs));
+    }
+
+    #[test]
+    fn playlists_by_id() {
+        let a = json!({"id": "a", "title": "A"});
+        let b = json!({"id": "b", "title": "B"});
+        let c = json!({"id": "c", "title": "C"});
+        let base = json!([a, b]);
+
+        // we renamed a, they removed b and added c
+        let ours = json!([{"id": "a", "title": "A2"}, b]);
+        let theirs = json!([a, c]);
+        assert_eq!(
+            Some(json!([{"id": "a", "title": "A2"}, c])),
+            merge(&base, &ours, &theirs)
+        );
+
+        // we changed b, which they removed
+        let ours = json!([a, {"id": "b", "title": "B2"}]);
+        assert_eq!(None, merge(&base, &ours, &theirs));
+
+        // same item added on both sides
+        assert_eq!(
+            Some(json!([a, b, c])),
+            merge(&base, &json!([a, b, c]), &json!([a, b, c]))
+        );
+    }
+
+    #[test]
+    fn scalars_and_plain_arrays() {
+        assert_eq!(Some(json!(2)), merge(&json!(1), &json!(1), &json!(2)));
+        assert_eq!(None, merge(&json!(1), &json!(2), &json!(3)));
+        assert_eq!(None, merge(&json!(["x"]), &json!(["y"]), &json!(["z"])));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{Deserialize, Error, PathBuf, Serialize};
+use safeapi::{Multiaddr, Network};
+use std::fs;
+
+const NETWORK_FILENAME: &str = "network.json";
+
+// Network to connect to, last choice is kept in app data dir.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(tag = "network", rename_all = "camelCase")]
+pub(crate) enum NetworkSetting {
+    #[default]
+    Mainnet,
+    Alpha,
+    Custom { peers: Vec<String> }, // bootstrap peers, Multiaddr each
+    Mock,                          // in-memory, for offline development
+}
+
+impl NetworkSetting {
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let NetworkSetting::Custom { peers } = self else {
+            return Ok(());
+        };
+
+        if peers.is_empty() {
+            return Err(Error::Common(String::from(
+                "Custom network needs at least one bootstrap peer.",
+            )));
+        }
+        for peer in peers {
+            peer.parse::<Multiaddr>()
+                .map_err(|_| Error::InvalidAddress(peer.clone()))?;
+        }
+
+        Ok(())
+    }
+
+    // None for the mock network
+    pub(crate) fn to_network(&self) -> Option<Network> {
+        match self {
+            NetworkSetting::Mainnet => Some(Network::Mainnet),
+            NetworkSetting::Alpha => Some(Network::Alpha),
+            NetworkSetting::Custom { peers } => Some(Network::Local(peers.clone())),
+            NetworkSetting::Mock => None,
+        }
+    }
+
+    // None if nothing was chosen yet
+    pub(crate) fn load(app_root: &PathBuf) -> Result<Option<Self>, Error> {
+        let file = app_root.join(NETWORK_FILENAME);
+        if !file.exists() {
+            return Ok(None);
+        }
+
+        let bytes = fs::read(&file)
+            .map_err(|e| Error::Common(format!("Could not read {}: {}", file.display(), e)))?;
+        let setting: Self = serde_json::from_slice(&bytes).map_err(|e| {
+            Error::Common(format!("Corrupted network setting {}: {}", file.display(), e))
+        })?;
+        setting.validate()?;
+
+        Ok(Some(setting))
+    }
+
+    pub(crate) fn save(&self, app_root: &PathBuf) -> Result<(), Error> {
+        let file = app_root.join(NETWORK_FILENAME);
+        let bytes = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::Common(format!("Could not serialize network setting: {}", e)))?;
+
+        fs::write(&file, bytes).map_err(|e| {
+            Error::Common(format!(
+                "Could not save network setting {}: {}",
+                file.display(),
+                e
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEER: &str = "/ip4/127.0.0.1/udp/12000/quic-v1";
+
+    #[test]
+    fn validation() {
+        assert!(NetworkSetting::Mainnet.validate().is_ok());
+        assert!(NetworkSetting::Alpha.validate().is_ok());
+        assert!(NetworkSetting::Mock.validate().is_ok());
+
+        let custom = |peers: &[&str]| NetworkSetting::Custom {
+            peers: peers.iter().map(|p| p.to_string()).collect(),
+        };
+        assert!(custom(&[PEER]).validate().is_ok());
+        assert!(custom(&[PEER, "/ip4/10.0.0.2/tcp/4000"]).validate().is_ok());
+        assert!(custom(&[]).validate().is_err());
+        assert!(custom(&[PEER, "127.0.0.1:12000"]).validate().is_err());
+    }
+
+    #[test]
+    fn setting_save_load() {
+        let dir = std::env::temp_dir().join(format!("jams-network-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(None, NetworkSetting::load(&dir).unwrap());
+
+        let setting = NetworkSetting::Custom {
+            peers: vec![String::from(PEER)],
+        };
+        setting.save(&dir).unwrap();
+        assert_eq!(Some(setting), NetworkSetting::load(&dir).unwrap());
+
+        fs::write(dir.join(NETWORK_FILENAME), r#"{"network":"alpha"}"#).unwrap();
+        assert_eq!(Some(NetworkSetting::Alpha), NetworkSetting::load(&dir).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use crate::connection;
+use crate::diagnostics::{self, Operation};
+use crate::Error;
+use futures::future::{AbortHandle, AbortRegistration, Abortable, Aborted};
+use futures::lock::Mutex;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+pub(crate) const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Network operations in progress, so disconnect can wait for them or abort them.
+#[derive(Default)]
+pub(crate) struct InFlight {
+    next_id: u64,
+    running: BTreeMap<u64, (Operation, AbortHandle)>,
+    closing: bool, // disconnecting, no new operations
+}
+
+impl InFlight {
+    fn start(&mut self, operation: Operation) -> Result<(u64, AbortRegistration), Error> {
+        if self.closing {
+            return Err(Error::Disconnecting);
+        }
+
+        let (handle, registration) = AbortHandle::new_pair();
+        self.next_id += 1;
+        self.running.insert(self.next_id, (operation, handle));
+        Ok((self.next_id, registration))
+    }
+
+    fn finish(&mut self, id: u64) {
+        self.running.remove(&id);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.running.is_empty()
+    }
+
+    // returns what was aborted, in order of starting
+    fn abort_all(&mut self) -> Vec<Operation> {
+        std::mem::take(&mut self.running)
+            .into_values()
+            .map(|(operation, handle)| {
+                handle.abort();
+                operation
+            })
+            .collect()
+    }
+}
+
+// Runs a network operation, so that it can be drained on disconnect, and records its outcome.
+// Network failure triggers a health check.
+pub(crate) async fn run<T>(
+    app: &AppHandle,
+    operation: Operation,
+    future: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    let in_flight = app.state::<Mutex<InFlight>>();
+    let (id, registration) = in_flight.lock().await.start(operation)?;
+
+    let start = Instant::now();
+    let result = match Abortable::new(future, registration).await {
+        Ok(result) => result,
+        Err(Aborted) => Err(Error::Aborted),
+    };
+    in_flight.lock().await.finish(id);
+
+    // only outcomes of calls that reached the network
+    if !matches!(result, Err(Error::Aborted) | Err(Error::NotConnected)) {
+        diagnostics::record(app, operation, start.elapsed(), result.as_ref().err()).await;
+        if result.is_err() {
+            connection::operation_failed(app);
+        }
+    }
+    result
+}
+
+// Rejects new operations, waits up to timeout for running ones, then aborts the rest.
+pub(crate) async fn drain(app: &AppHandle, timeout: Duration) -> Vec<Operation> {
+    let in_flight = app.state::<Mutex<InFlight>>();
+    in_flight.lock().await.closing = true;
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline && !in_flight.lock().await.is_empty() {
+        tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+    }
+
+    let aborted = in_flight.lock().await.abort_all();
+    if !aborted.is_empty() {
+        warn!(?aborted, "Aborted on disconnect.");
+    }
+    aborted
+}
+
+// accept operations again, after disconnect finished
+pub(crate) async fn reopen(app: &AppHandle) {
+    app.state::<Mutex<InFlight>>().lock().await.closing = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_flight() {
+        let mut in_flight = InFlight::default();
+        let (upload, _) = in_flight.start(Operation::Upload).unwrap();
+        let (download, _) = in_flight.start(Operation::Download).unwrap();
+        let (_, read_reg) = in_flight.start(Operation::ReadReg).unwrap();
+        assert_ne!(upload, download);
+
+        in_flight.finish(download);
+        in_flight.closing = true;
+        assert!(matches!(
+            in_flight.start(Operation::Balance),
+            Err(Error::Disconnecting)
+        ));
+
+        assert_eq!(
+            vec![Operation::Upload, Operation::ReadReg],
+            in_flight.abort_all()
+        );
+        assert!(in_flight.is_empty());
+        in_flight.finish(upload); // finishing after abort is fine
+
+        // aborted registration makes the operation end with Aborted
+        let aborted = futures::executor::block_on(Abortable::new(async { 1 }, read_reg));
+        assert_eq!(Err(Aborted), aborted);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::network::NetworkSetting;
+use crate::{Deserialize, Error, PathBuf, Serialize};
+use serde::Deserializer;
+use serde_json::Value;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const PROFILE_FILENAME: &str = "profile.json";
+
+// Non-secret account data, stored next to the key in account directory.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AccountProfile {
+    pub(crate) display_name: String,
+    pub(crate) created_at: u64,              // seconds since UNIX epoch
+    pub(crate) last_sign_in_at: Option<u64>, // seconds since UNIX epoch
+    #[serde(default, deserialize_with = "network_or_legacy")]
+    pub(crate) preferred_network: Option<NetworkSetting>,
+    pub(crate) avatar: Option<String>, // e.g. xorname or file path of the picture
+}
+
+// Profiles used to keep just a network name, unknown ones are dropped.
+fn network_or_legacy<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NetworkSetting>, D::Error> {
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::String(name)) => {
+            serde_json::from_value(serde_json::json!({ "network": name })).ok()
+        }
+        Some(value) => serde_json::from_value(value).ok(),
+        None => None,
+    })
+}
+
+pub(crate) fn now() -> u64 {
+    to_timestamp(SystemTime::now())
+}
+
+fn to_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl AccountProfile {
+    pub(crate) fn new(display_name: String) -> Self {
+        AccountProfile {
+            display_name,
+            created_at: now(),
+            last_sign_in_at: None,
+            preferred_network: None,
+            avatar: None,
+        }
+    }
+
+    // None if the account has no profile yet
+    pub(crate) fn load(account_dir: &PathBuf) -> Result<Option<Self>, Error> {
+        let file = account_dir.join(PROFILE_FILENAME);
+        if !file.exists() {
+            return Ok(None);
+        }
+
+        let bytes = fs::read(&file)
+            .map_err(|e| Error::Common(format!("Could not read {}: {}", file.display(), e)))?;
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| Error::Common(format!("Corrupted profile {}: {}", file.display(), e)))
+    }
+
+    pub(crate) fn save(&self, account_dir: &PathBuf) -> Result<(), Error> {
+        let file = account_dir.join(PROFILE_FILENAME);
+        let bytes = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::Common(format!("Could not serialize profile: {}", e)))?;
+
+        fs::write(&file, bytes)
+            .map_err(|e| Error::Common(format!("Could not save profile {}: {}", file.display(), e)))
+    }
+
+    // Profile for accounts created before profiles existed. Address file used to be
+    // rewritten on every sign in, so its modification time is the best guess we have.
+    pub(crate) fn from_legacy(display_name: String, account_dir: &PathBuf) -> Self {
+        let modified = |name: &str| {
+            account_dir
+                .join(name)
+                .metadata()
+                .and_then(|m| m.modified())
+                .map(to_timestamp)
+                .ok()
+        };
+
+        let last_sign_in_at = modified(crate::ADDRESS_FILENAME);
+        AccountProfile {
+            created_at: modified(crate::SK_FILENAME)
+                .or(last_sign_in_at)
+                .unwrap_or_else(now),
+            last_sign_in_at,
+            ..AccountProfile::new(display_name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_save_load() {
+        let dir = std::env::temp_dir().join(format!("jams-profile-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(None, AccountProfile::load(&dir).unwrap());
+
+        let mut profile = AccountProfile::new("Alice".into());
+        profile.last_sign_in_at = Some(profile.created_at + 10);
+        profile.preferred_network = Some(NetworkSetting::Custom {
+            peers: vec!["/ip4/127.0.0.1/udp/12000/quic-v1".into()],
+        });
+        profile.save(&dir).unwrap();
+        assert_eq!(Some(profile), AccountProfile::load(&dir).unwrap());
+
+        // plain network names of older profiles
+        let legacy = |network: &str| {
+            let json = format!(
+                r#"{{"displayName":"A","createdAt":1,"lastSignInAt":null,"preferredNetwork":{},"avatar":null}}"#,
+                network
+            );
+            serde_json::from_str::<AccountProfile>(&json).unwrap().preferred_network
+        };
+        assert_eq!(Some(NetworkSetting::Alpha), legacy(r#""alpha""#));
+        assert_eq!(None, legacy(r#""moon""#));
+        assert_eq!(None, legacy("null"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn legacy_profile() {
+        let dir = std::env::temp_dir().join(format!("jams-legacy-profile-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let profile = AccountProfile::from_legacy("bob".into(), &dir);
+        assert_eq!(None, profile.last_sign_in_at);
+
+        fs::write(dir.join(crate::ADDRESS_FILENAME), "0x1234").unwrap();
+        let profile = AccountProfile::from_legacy("bob".into(), &dir);
+        assert!(profile.last_sign_in_at.is_some());
+        assert_eq!("bob", profile.display_name);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
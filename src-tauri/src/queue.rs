@@ -0,0 +1,398 @@
+use crate::account_name::AccountName;
+use crate::registers::{RegisterKey, Schema};
+use crate::wallet::WalletState;
+use crate::{profile, Client, Deserialize, Error, PathBuf, Serialize};
+use futures::lock::Mutex;
+use std::fs;
+use std::io::Write;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{error, info, warn};
+
+const INDEX_FILENAME: &str = "index.json";
+const SEALED_DATA_AAD: &[u8] = b"jams queued register";
+
+// empty if there is no index yet
+fn read_index(index: &PathBuf) -> Result<Vec<QueueItem>, Error> {
+    if !index.exists() {
+        return Ok(vec![]);
+    }
+    let bytes = fs::read(index)
+        .map_err(|e| Error::Common(format!("Could not read {}: {}", index.display(), e)))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| Error::Common(format!("Corrupted offline queue {}: {}", index.display(), e)))
+}
+
+// Network write postponed until connected.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum QueuedOp {
+    Upload { file: String },
+    PutData { size: usize }, // data itself is kept in <id>.bin
+    WriteReg {
+        name: Vec<String>,
+        schema: Schema,
+        data: String, // JSON, or hex of it encrypted if private, not to be stored in plaintext
+        #[serde(default)]
+        private: bool, // encrypted when written
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueueItem {
+    pub(crate) id: u64,
+    pub(crate) login: String,   // replayed only for the account that queued it
+    pub(crate) created_at: u64, // seconds since UNIX epoch
+    #[serde(flatten)]
+    pub(crate) op: QueuedOp,
+    pub(crate) attempts: u32,
+    pub(crate) last_error: Option<String>,
+}
+
+// payload of "queue_item_done" and "queue_item_failed" events
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueueItemResult {
+    pub(crate) id: u64,
+    pub(crate) xorname: Option<String>, // of uploaded data
+    pub(crate) error: Option<String>,
+}
+
+// Persistent, ordered queue of network writes made while offline.
+pub(crate) struct OfflineQueue {
+    dir: PathBuf,
+    items: Vec<QueueItem>, // oldest first
+    next_id: u64,          // ids are not reused while the app runs
+    replaying: bool,
+}
+
+impl OfflineQueue {
+    pub(crate) fn open(dir: PathBuf) -> Result<Self, Error> {
+        fs::create_dir_all(&dir).map_err(|e| {
+            Error::Common(format!("Could not create queue dir {}: {}", dir.display(), e))
+        })?;
+
+        let index = dir.join(INDEX_FILENAME);
+        let items = match read_index(&index) {
+            Ok(items) => items,
+            Err(e) => {
+                // queued writes are lost, but the app still starts
+                let aside = index.with_extension(format!("json.bad-{}", profile::now()));
+                match fs::rename(&index, &aside) {
+                    Ok(()) => error!("{} Moved to {}.", e, aside.display()),
+                    Err(rename_error) => error!("{} Could not move it: {}", e, rename_error),
+                }
+                vec![]
+            }
+        };
+
+        let next_id = items.iter().map(|item: &QueueItem| item.id).max().unwrap_or(0) + 1;
+        Ok(OfflineQueue {
+            dir,
+            items,
+            next_id,
+            replaying: false,
+        })
+    }
+
+    fn payload_file(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{}.bin", id))
+    }
+
+    // written to a temporary file first, so a crash cannot lose the whole queue
+    fn save(&self) -> Result<(), Error> {
+        let index = self.dir.join(INDEX_FILENAME);
+        let tmp = index.with_extension("json.tmp");
+        let bytes = serde_json::to_vec_pretty(&self.items)
+            .map_err(|e| Error::Common(format!("Could not serialize offline queue: {}", e)))?;
+
+        let write_tmp = || -> std::io::Result<()> {
+            let mut file = fs::File::create(&tmp)?;
+            file.write_all(&bytes)?;
+            file.sync_all()?;
+            fs::rename(&tmp, &index)
+        };
+        write_tmp()
+            .map_err(|e| Error::Common(format!("Could not save {}: {}", index.display(), e)))
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        login: &AccountName,
+        op: QueuedOp,
+        payload: Option<&[u8]>,
+    ) -> Result<QueueItem, Error> {
+        let item = QueueItem {
+            id: self.next_id,
+            login: login.to_string(),
+            created_at: profile::now(),
+            op,
+            attempts: 0,
+            last_error: None,
+        };
+
+        if let Some(payload) = payload {
+            let file = self.payload_file(item.id);
+            fs::write(&file, payload).map_err(|e| {
+                Error::Common(format!("Could not save queued data {}: {}", file.display(), e))
+            })?;
+        }
+
+        self.next_id += 1;
+        self.items.push(item.clone());
+        self.save()?;
+        Ok(item)
+    }
+
+    pub(crate) fn list(&self) -> Vec<QueueItem> {
+        self.items.clone()
+    }
+
+    pub(crate) fn first(&self, login: &AccountName) -> Option<QueueItem> {
+        self.items
+            .iter()
+            .find(|item| item.login == login.as_str())
+            .cloned()
+    }
+
+    pub(crate) fn payload(&self, id: u64) -> Result<Vec<u8>, Error> {
+        let file = self.payload_file(id);
+        fs::read(&file)
+            .map_err(|e| Error::Common(format!("Could not read queued data {}: {}", file.display(), e)))
+    }
+
+    // done or cancelled, returns None if there is no such item (anymore)
+    pub(crate) fn remove(&mut self, id: u64) -> Result<Option<QueueItem>, Error> {
+        let Some(position) = self.items.iter().position(|item| item.id == id) else {
+            return Ok(None);
+        };
+
+        let item = self.items.remove(position);
+        self.save()?;
+        let _ = fs::remove_file(self.payload_file(id)); // there is none for most items
+        Ok(Some(item))
+    }
+
+    pub(crate) fn failed(&mut self, id: u64, error: String) -> Result<(), Error> {
+        if let Some(item) = self.items.iter_mut().find(|item| item.id == id) {
+            item.attempts += 1;
+            item.last_error = Some(error);
+            self.save()?;
+        }
+        Ok(())
+    }
+}
+
+// Data of a private register write, encrypted with the account's key while queued.
+pub(crate) fn seal_data(data: &str, key: &RegisterKey) -> Result<String, Error> {
+    Ok(hex::encode(key.encrypt(data.as_bytes(), SEALED_DATA_AAD)?))
+}
+
+fn open_data(sealed: &str, key: &RegisterKey) -> Result<String, Error> {
+    let sealed = hex::decode(sealed)
+        .map_err(|_| Error::InvalidDocument(String::from("Corrupted queued register data.")))?;
+    String::from_utf8(key.decrypt(&sealed, SEALED_DATA_AAD)?)
+        .map_err(|_| Error::InvalidDocument(String::from("Corrupted queued register data.")))
+}
+
+// returns xorname of uploaded data
+async fn execute(
+    app: &AppHandle,
+    item: &QueueItem,
+    payload: Option<Vec<u8>>,
+) -> Result<Option<String>, Error> {
+    match &item.op {
+        QueuedOp::Upload { file } => crate::upload(file.clone(), app.clone()).await.map(Some),
+        QueuedOp::PutData { .. } => {
+            let data = payload.ok_or(Error::Common(String::from("Queued data is missing.")))?;
+            crate::put_data(data, app.clone()).await.map(Some)
+        }
+        QueuedOp::WriteReg {
+            name,
+            schema,
+            data,
+            private,
+        } => crate::write_reg(
+            name.clone(),
+            *schema,
+            if *private {
+                open_data(data, &crate::register_key(&app.state()).await?)?
+            } else {
+                data.clone()
+            },
+            *private,
+            None, // written as queued, whatever happened meanwhile
+            app.state::<Client>(),
+            app.state::<Mutex<WalletState>>(),
+            app.clone(),
+        )
+        .await
+        .map(|_| None),
+    }
+}
+
+// replays in background, to be called when connected or signed in
+pub(crate) fn schedule_replay(app: &AppHandle) {
+    tauri::async_runtime::spawn(replay(app.clone()));
+}
+
+// Runs items queued by the signed in user in order, stops at the first failure to keep the order.
+pub(crate) async fn replay(app: AppHandle) {
+    let login = {
+        let wallet = app.state::<Mutex<WalletState>>();
+        let wallet = wallet.lock().await;
+        match &wallet.login {
+            Some(login) if !wallet.watch_only && !wallet.locked => login.clone(),
+            _ => return, // nobody can pay for it now
+        }
+    };
+
+    let queue = app.state::<Mutex<OfflineQueue>>();
+    {
+        let mut queue = queue.lock().await;
+        if queue.replaying {
+            return;
+        }
+        queue.replaying = true;
+    }
+
+    loop {
+        let (item, payload) = {
+            let items = queue.lock().await;
+            let Some(item) = items.first(&login) else {
+                break;
+            };
+            let payload = match item.op {
+                QueuedOp::PutData { .. } => items.payload(item.id).ok(),
+                _ => None,
+            };
+            (item, payload)
+        };
+
+        info!(id = item.id, op = ?item.op, "Replaying queued item.");
+        let result = execute(&app, &item, payload).await;
+
+        let stored = {
+            let mut items = queue.lock().await;
+            match &result {
+                Ok(_) => items.remove(item.id).map(|_| ()),
+                Err(e) => items.failed(item.id, format!("{}", e)),
+            }
+        };
+        if let Err(e) = stored {
+            error!("{}", e);
+        }
+
+        match result {
+            Ok(xorname) => {
+                let _ = app
+                    .emit(
+                        "queue_item_done",
+                        QueueItemResult {
+                            id: item.id,
+                            xorname,
+                            error: None,
+                        },
+                    )
+                    .inspect_err(|e| error!("{}", e));
+            }
+            Err(e) => {
+                warn!(id = item.id, "Queued item failed: {}", e);
+                let _ = app
+                    .emit(
+                        "queue_item_failed",
+                        QueueItemResult {
+                            id: item.id,
+                            xorname: None,
+                            error: Some(format!("{}", e)),
+                        },
+                    )
+                    .inspect_err(|e| error!("{}", e));
+                break;
+            }
+        }
+    }
+
+    queue.lock().await.replaying = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_persistence() {
+        let dir = std::env::temp_dir().join(format!("jams-queue-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let alice = AccountName::parse("alice").unwrap();
+        let bob = AccountName::parse("bob").unwrap();
+        let mut queue = OfflineQueue::open(dir.clone()).unwrap();
+        assert_eq!(None, queue.first(&alice));
+
+        let upload = queue
+            .push(&alice, QueuedOp::Upload { file: "song.mp3".into() }, None)
+            .unwrap();
+        let put_data = queue
+            .push(&alice, QueuedOp::PutData { size: 3 }, Some(&[1, 2, 3]))
+            .unwrap();
+        let write_reg = queue
+            .push(
+                &bob,
+                QueuedOp::WriteReg {
+                    name: vec!["jams".into(), "playlists".into()],
+                    schema: Schema::Playlists,
+                    data: "[]".into(),
+                    private: true,
+                },
+                None,
+            )
+            .unwrap();
+        assert_ne!(upload.id, put_data.id);
+        assert_eq!(Some(upload.clone()), queue.first(&alice));
+        assert_eq!(Some(write_reg.clone()), queue.first(&bob));
+
+        queue.failed(upload.id, "offline".into()).unwrap();
+        assert!(queue.remove(write_reg.id).unwrap().is_some()); // cancelled
+        assert!(queue.remove(write_reg.id).unwrap().is_none());
+        assert_eq!(None, queue.first(&bob));
+
+        // survives restart
+        let mut queue = OfflineQueue::open(dir.clone()).unwrap();
+        let items = queue.list();
+        assert_eq!(2, items.len());
+        assert_eq!(upload.id, items[0].id);
+        assert_eq!(1, items[0].attempts);
+        assert_eq!(Some(String::from("offline")), items[0].last_error);
+        assert_eq!(vec![1, 2, 3], queue.payload(put_data.id).unwrap());
+
+        queue.remove(put_data.id).unwrap();
+        assert!(queue.payload(put_data.id).is_err());
+
+        // corrupted index is moved aside, the queue starts empty
+        fs::write(dir.join(INDEX_FILENAME), "{not json").unwrap();
+        let queue = OfflineQueue::open(dir.clone()).unwrap();
+        assert!(queue.list().is_empty());
+        assert!(!dir.join(INDEX_FILENAME).exists());
+        assert!(fs::read_dir(&dir)
+            .unwrap()
+            .any(|entry| entry.unwrap().file_name().to_string_lossy().contains(".bad-")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn private_data() {
+        let key = RegisterKey::new("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+            .unwrap();
+        let other = RegisterKey::new("59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d")
+            .unwrap();
+        let data = r#"[{"id":"p","title":"Secret"}]"#;
+
+        let sealed = seal_data(data, &key).unwrap();
+        assert!(!sealed.contains("Secret"));
+        assert_eq!(data, open_data(&sealed, &key).unwrap());
+        assert!(open_data(&sealed, &other).is_err());
+        assert!(open_data(data, &key).is_err());
+    }
+}
@@ -0,0 +1,536 @@
+use crate::profile;
+use crate::{keys, Deserialize, Error, Serialize, XorName};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde_json::Value;
+use sha3::{Digest, Sha3_256};
+
+const NONCE_LEN: usize = 12; // AES-GCM standard nonce
+// Bigger payloads are stored as immutable data, register entries are size-limited.
+const MAX_INLINE_PAYLOAD: usize = 4 * 1024;
+
+// Kinds of documents kept in registers, each checked against its Rust type.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Schema {
+    Playlists, // Vec<Playlist>
+    Library,   // Vec<Song>
+    Profile,   // PublicProfile
+}
+
+impl Schema {
+    // bumped when the payload format changes, with a migration added
+    pub(crate) fn current_version(&self) -> u32 {
+        match self {
+            Schema::Playlists => 1,
+            Schema::Library => 1,
+            Schema::Profile => 1,
+        }
+    }
+
+    // payload of a register created without data
+    fn initial(&self) -> Value {
+        match self {
+            Schema::Playlists | Schema::Library => Value::Array(vec![]),
+            Schema::Profile => serde_json::json!({ "displayName": "" }),
+        }
+    }
+
+    pub(crate) fn validate(&self, payload: &Value) -> Result<(), Error> {
+        let result = match self {
+            Schema::Playlists => Vec::<Playlist>::deserialize(payload).map(|_| ()),
+            Schema::Library => Vec::<Song>::deserialize(payload).map(|_| ()),
+            Schema::Profile => PublicProfile::deserialize(payload).map(|_| ()),
+        };
+        result.map_err(|e| Error::InvalidDocument(format!("{:?}: {}", self, e)))
+    }
+}
+
+// Same as frontend types. Unknown fields are kept in the register, just not checked.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Song {
+    pub(crate) id: String,
+    pub(crate) xorname: String,
+    pub(crate) title: String,
+    pub(crate) artist: Option<String>,
+    pub(crate) album: Option<String>,
+    pub(crate) genre: Option<String>,
+    pub(crate) date_created: String, // ISO 8601, as serialized by JS Date
+    pub(crate) date_updated: Option<String>,
+    pub(crate) file_name: String,
+    pub(crate) extension: String,
+    pub(crate) download_folder: Option<String>,
+    pub(crate) tags: Option<Vec<String>>,
+    pub(crate) picture: Option<String>,
+    pub(crate) track_number: Option<u32>,
+    pub(crate) year: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Playlist {
+    pub(crate) id: String,
+    pub(crate) picture: Option<String>,
+    pub(crate) title: String,
+    pub(crate) description: Option<String>,
+    pub(crate) created_at: String, // ISO 8601
+    pub(crate) updated_at: String,
+    pub(crate) songs: Option<Vec<Song>>,
+    pub(crate) tags: Option<Vec<String>>,
+}
+
+// What others see of an account, unlike AccountProfile, which stays on this device.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PublicProfile {
+    pub(crate) display_name: String,
+    pub(crate) bio: Option<String>,
+    pub(crate) avatar: Option<String>, // xorname of the picture
+}
+
+// What is actually stored in a register.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Envelope {
+    pub(crate) schema: Schema,
+    pub(crate) version: u32,
+    #[serde(default)]
+    pub(crate) revision: u32, // 0 when created, +1 on each write, safeapi has no head version
+    pub(crate) writer: Option<String>, // EVM address, None for registers written before envelopes
+    pub(crate) written_at: u64,        // seconds since UNIX epoch, 0 if unknown
+    #[serde(default)]
+    pub(crate) encrypted: bool, // payload readable only by the writer account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) pointer: Option<PayloadPointer>, // payload is in immutable data, if set
+    pub(crate) payload: Value, // hex of nonce and ciphertext, if encrypted; null if pointer
+}
+
+// Where a payload too big for the register is, and how to verify it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PayloadPointer {
+    pub(crate) xorname: String, // hex
+    pub(crate) sha3: String,    // hex of SHA3-256 of the data
+    pub(crate) size: usize,
+}
+
+// Key of the signed in account for private registers.
+pub(crate) struct RegisterKey {
+    address: String,
+    key: [u8; 32],
+}
+
+impl RegisterKey {
+    pub(crate) fn new(eth_pk: &str) -> Result<Self, Error> {
+        Ok(RegisterKey {
+            address: keys::eth_address(eth_pk)?,
+            key: keys::register_key(eth_pk)?,
+        })
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.key))
+    }
+
+    // nonce followed by ciphertext
+    pub(crate) fn encrypt(&self, msg: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, Payload { msg, aad })
+            .map_err(|_| Error::Common(String::from("Could not encrypt register.")))?;
+        Ok([nonce.as_slice(), ciphertext.as_slice()].concat())
+    }
+
+    pub(crate) fn decrypt(&self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+        if sealed.len() <= NONCE_LEN {
+            return Err(Error::InvalidDocument(String::from("Corrupted private register.")));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher()
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| Error::InvalidDocument(String::from("Could not decrypt register.")))
+    }
+}
+
+impl Envelope {
+    // Wraps JSON from frontend, empty data is the initial value of the schema.
+    pub(crate) fn new(schema: Schema, data: &str, writer: String) -> Result<Self, Error> {
+        Ok(Envelope {
+            schema,
+            version: schema.current_version(),
+            revision: 0,
+            writer: Some(writer),
+            written_at: profile::now(),
+            encrypted: false,
+            pointer: None,
+            payload: parse_payload(schema, data)?,
+        })
+    }
+
+    // Encrypts payload for the account of the key, who has to be the writer,
+    // bound to the register at meta.
+    pub(crate) fn seal(mut self, key: &RegisterKey, meta: &XorName) -> Result<Self, Error> {
+        if !self.written_by(key) {
+            return Err(Error::Common(String::from(
+                "Private register has to be written by its owner.",
+            )));
+        }
+
+        let plaintext = serde_json::to_vec(&self.payload)
+            .map_err(|e| Error::Common(format!("Could not serialize register: {}", e)))?;
+        let sealed = key.encrypt(&plaintext, &self.associated_data(meta))?;
+        self.payload = Value::String(hex::encode(sealed));
+        self.encrypted = true;
+        Ok(self)
+    }
+
+    // Payload to be stored as immutable data, None if it fits in the register.
+    pub(crate) fn oversized_payload(&self) -> Result<Option<Vec<u8>>, Error> {
+        let payload = serde_json::to_vec(&self.payload)
+            .map_err(|e| Error::Common(format!("Could not serialize register: {}", e)))?;
+        Ok((payload.len() > MAX_INLINE_PAYLOAD).then_some(payload))
+    }
+
+    // Replaces payload with a pointer to where it was stored.
+    pub(crate) fn point_to(mut self, xorname: XorName, payload: &[u8]) -> Self {
+        self.pointer = Some(PayloadPointer {
+            xorname: hex::encode(xorname),
+            sha3: hex::encode(Sha3_256::digest(payload)),
+            size: payload.len(),
+        });
+        self.payload = Value::Null;
+        self
+    }
+
+    // Puts back payload downloaded from the pointer, after checking it is the one written.
+    pub(crate) fn resolve(mut self, payload: &[u8]) -> Result<Self, Error> {
+        let Some(pointer) = self.pointer.take() else {
+            return Ok(self);
+        };
+        let hash = hex::encode(Sha3_256::digest(payload));
+        if payload.len() != pointer.size || hash != pointer.sha3 {
+            return Err(Error::InvalidDocument(format!(
+                "Register data {} does not match its hash.",
+                pointer.xorname
+            )));
+        }
+
+        self.payload = serde_json::from_slice(payload)
+            .map_err(|e| Error::InvalidDocument(format!("Corrupted register data: {}", e)))?;
+        if self.encrypted {
+            return Ok(self); // to be opened
+        }
+        self.checked()
+    }
+
+    // Decrypts, upgrades and checks payload of a private register at meta, read by from_bytes.
+    pub(crate) fn open(mut self, key: &RegisterKey, meta: &XorName) -> Result<Self, Error> {
+        if !self.encrypted {
+            return Ok(self);
+        }
+        if self.pointer.is_some() {
+            return Err(Error::Common(String::from("Register data is not downloaded yet.")));
+        }
+        if !self.written_by(key) {
+            return Err(Error::WrongAccount(self.writer.unwrap_or_default()));
+        }
+
+        let sealed = self
+            .payload
+            .as_str()
+            .and_then(|payload| hex::decode(payload).ok())
+            .ok_or(Error::InvalidDocument(String::from("Corrupted private register.")))?;
+        let plaintext = key.decrypt(&sealed, &self.associated_data(meta))?;
+        self.payload = serde_json::from_slice(&plaintext)
+            .map_err(|e| Error::InvalidDocument(format!("Corrupted private register: {}", e)))?;
+
+        self.checked()
+    }
+
+    // upgraded to the current version and matching the schema
+    fn checked(self) -> Result<Self, Error> {
+        let envelope = migrate(self)?;
+        envelope.schema.validate(&envelope.payload)?;
+        Ok(envelope)
+    }
+
+    fn written_by(&self, key: &RegisterKey) -> bool {
+        self.writer
+            .as_deref()
+            .is_some_and(|writer| keys::same_address(writer, &key.address))
+    }
+
+    // Binds ciphertext to its envelope and register, so it can't be moved to another
+    // register or passed off as another revision.
+    fn associated_data(&self, meta: &XorName) -> Vec<u8> {
+        format!(
+            "{:?}/{}/{}/{}/{}",
+            self.schema,
+            self.version,
+            self.writer.as_deref().unwrap_or_default(),
+            hex::encode(meta),
+            self.revision
+        )
+        .into_bytes()
+    }
+
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(self)
+            .map_err(|e| Error::Common(format!("Could not serialize register: {}", e)))
+    }
+
+    // Reads register bytes, upgrading old versions. Bare JSON is a register from before
+    // envelopes, taken as version 0 of the expected schema. Private registers are left
+    // encrypted, to be opened with the key, and pointers to be resolved with downloaded data.
+    pub(crate) fn from_bytes(bytes: &[u8], expected: Schema) -> Result<Self, Error> {
+        let value: Value = serde_json::from_slice(bytes)
+            .map_err(|e| Error::InvalidDocument(format!("Register is not JSON: {}", e)))?;
+
+        let envelope = if is_envelope(&value) {
+            serde_json::from_value(value)
+                .map_err(|e| Error::InvalidDocument(format!("Corrupted register envelope: {}", e)))?
+        } else {
+            Envelope {
+                schema: expected,
+                version: 0,
+                revision: 0,
+                writer: None,
+                written_at: 0,
+                encrypted: false,
+                pointer: None,
+                payload: value,
+            }
+        };
+
+        if envelope.schema != expected {
+            return Err(Error::InvalidDocument(format!(
+                "Expected {:?} register, found {:?}.",
+                expected, envelope.schema
+            )));
+        }
+        if envelope.version > expected.current_version() {
+            return Err(Error::InvalidDocument(format!(
+                "{:?} register version {} is written by a newer app version.",
+                expected, envelope.version
+            )));
+        }
+
+        if envelope.encrypted || envelope.pointer.is_some() {
+            return Ok(envelope);
+        }
+        envelope.checked()
+    }
+}
+
+// Entry of register history, readable even if the value itself is not valid.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RegisterVersion {
+    pub(crate) version: u32, // 0 is the first value
+    pub(crate) writer: Option<String>,
+    pub(crate) written_at: u64, // 0 if unknown
+    pub(crate) size: usize,     // bytes
+}
+
+// Versions of a register, oldest first.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RegisterHistory {
+    pub(crate) versions: Vec<RegisterVersion>,
+    pub(crate) complete: bool, // false if too long, newest versions are missing
+}
+
+// Revision of raw register bytes, without opening them. 0 for bare JSON or anything unreadable.
+pub(crate) fn revision(bytes: &[u8]) -> u32 {
+    serde_json::from_slice::<Value>(bytes)
+        .ok()
+        .filter(is_envelope)
+        .and_then(|value| value["revision"].as_u64())
+        .and_then(|revision| u32::try_from(revision).ok())
+        .unwrap_or(0)
+}
+
+impl RegisterVersion {
+    pub(crate) fn new(version: u32, bytes: &[u8]) -> Self {
+        let value = serde_json::from_slice::<Value>(bytes)
+            .ok()
+            .filter(is_envelope)
+            .unwrap_or_default();
+
+        RegisterVersion {
+            version,
+            writer: value["writer"].as_str().map(String::from),
+            written_at: value["writtenAt"].as_u64().unwrap_or(0),
+            size: bytes.len(),
+        }
+    }
+}
+
+// Checks JSON from frontend against the schema.
+pub(crate) fn parse_payload(schema: Schema, data: &str) -> Result<Value, Error> {
+    let payload = if data.is_empty() {
+        schema.initial()
+    } else {
+        serde_json::from_str(data).map_err(|e| Error::InvalidDocument(format!("{}", e)))?
+    };
+    schema.validate(&payload)?;
+    Ok(payload)
+}
+
+fn is_envelope(value: &Value) -> bool {
+    value
+        .as_object()
+        .is_some_and(|o| o.contains_key("schema") && o.contains_key("version") && o.contains_key("payload"))
+}
+
+// Upgrades payload one version at a time, up to the current one.
+fn migrate(mut envelope: Envelope) -> Result<Envelope, Error> {
+    while envelope.version < envelope.schema.current_version() {
+        envelope.payload = match (envelope.schema, envelope.version) {
+            // bare JSON had the same format as version 1
+            (_, 0) => envelope.payload,
+            (schema, version) => {
+                return Err(Error::InvalidDocument(format!(
+                    "No migration of {:?} register from version {}.",
+                    schema, version
+                )))
+            }
+        };
+        envelope.version += 1;
+    }
+    Ok(envelope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SONG: &str = r#"{"id":"1","xorname":"08db","title":"Beg Blag",
+        "dateCreated":"2024-11-02T10:00:00.000Z","fileName":"BegBlag","extension":"mp3",
+        "downloadFolder":null,"rating":5}"#;
+
+    #[test]
+    fn envelope() {
+        let library = format!("[{}]", SONG);
+        let envelope = Envelope::new(Schema::Library, &library, "0xabc".into()).unwrap();
+        assert_eq!(1, envelope.version);
+        assert_eq!(5, envelope.payload[0]["rating"]); // unknown fields kept
+
+        let bytes = envelope.to_bytes().unwrap();
+        assert_eq!(envelope, Envelope::from_bytes(&bytes, Schema::Library).unwrap());
+        assert!(Envelope::from_bytes(&bytes, Schema::Playlists).is_err());
+
+        assert_eq!(
+            Value::Array(vec![]),
+            Envelope::new(Schema::Playlists, "", "0xabc".into()).unwrap().payload
+        );
+        assert_eq!(
+            serde_json::json!({ "displayName": "" }),
+            Envelope::new(Schema::Profile, "", "0xabc".into()).unwrap().payload
+        );
+        assert!(Envelope::new(Schema::Profile, r#"{"bio":"x"}"#, "0xabc".into()).is_err());
+        assert!(Envelope::new(Schema::Library, r#"[{"id":"1"}]"#, "0xabc".into()).is_err());
+        assert!(Envelope::new(Schema::Library, "not json", "0xabc".into()).is_err());
+    }
+
+    #[test]
+    fn private() {
+        let alice = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let bob = "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+        let alice_key = RegisterKey::new(alice).unwrap();
+        let bob_key = RegisterKey::new(bob).unwrap();
+
+        let playlists = format!(
+            r#"[{{"id":"p","title":"Mine","createdAt":"x","updatedAt":"x","songs":[{}]}}]"#,
+            SONG
+        );
+        let envelope =
+            Envelope::new(Schema::Playlists, &playlists, alice_key.address.clone()).unwrap();
+        let meta = XorName([1; 32]);
+        let sealed = envelope.clone().seal(&alice_key, &meta).unwrap();
+        assert!(sealed.encrypted);
+        assert!(!sealed.to_bytes().unwrap().windows(4).any(|w| w == b"Mine"));
+        assert!(envelope.clone().seal(&bob_key, &meta).is_err()); // not the writer
+
+        let read = Envelope::from_bytes(&sealed.to_bytes().unwrap(), Schema::Playlists).unwrap();
+        assert!(matches!(read.clone().open(&bob_key, &meta), Err(Error::WrongAccount(_))));
+        let opened = read.clone().open(&alice_key, &meta).unwrap();
+        assert_eq!(envelope.payload, opened.payload);
+
+        // tampered
+        let mut moved = sealed.clone();
+        moved.version = 0;
+        assert!(matches!(moved.open(&alice_key, &meta), Err(Error::InvalidDocument(_))));
+
+        // copied to another register of the same schema
+        let other = XorName([2; 32]);
+        assert!(matches!(read.clone().open(&alice_key, &other), Err(Error::InvalidDocument(_))));
+
+        // rolled back, old ciphertext under a newer revision
+        let mut rolled_back = read;
+        rolled_back.revision = 5;
+        assert!(matches!(rolled_back.open(&alice_key, &meta), Err(Error::InvalidDocument(_))));
+    }
+
+    #[test]
+    fn oversized() {
+        let song = |i: usize| SONG.replace(r#""id":"1""#, &format!(r#""id":"{}""#, i));
+        let library = format!("[{}]", (0..100).map(song).collect::<Vec<_>>().join(","));
+        let envelope = Envelope::new(Schema::Library, &library, "0xabc".into()).unwrap();
+        assert!(Envelope::new(Schema::Library, "[]", "0xabc".into())
+            .unwrap()
+            .oversized_payload()
+            .unwrap()
+            .is_none());
+
+        let payload = envelope.oversized_payload().unwrap().unwrap();
+        let xorname = XorName(Sha3_256::digest(&payload).into());
+        let bytes = envelope.clone().point_to(xorname, &payload).to_bytes().unwrap();
+        assert!(bytes.len() < MAX_INLINE_PAYLOAD);
+
+        let read = Envelope::from_bytes(&bytes, Schema::Library).unwrap();
+        assert_eq!(Value::Null, read.payload);
+        assert!(matches!(
+            read.clone().resolve(b"[]"),
+            Err(Error::InvalidDocument(_))
+        ));
+        assert_eq!(envelope, read.resolve(&payload).unwrap());
+    }
+
+    #[test]
+    fn history_entry() {
+        let envelope = Envelope::new(Schema::Playlists, "[]", "0xabc".into()).unwrap();
+        let bytes = envelope.to_bytes().unwrap();
+        let entry = RegisterVersion::new(3, &bytes);
+        assert_eq!(3, entry.version);
+        assert_eq!(Some(String::from("0xabc")), entry.writer);
+        assert_eq!(envelope.written_at, entry.written_at);
+        assert_eq!(bytes.len(), entry.size);
+
+        let legacy = RegisterVersion::new(0, b"not even json");
+        assert_eq!((None, 0, 13), (legacy.writer, legacy.written_at, legacy.size));
+
+        let mut written = envelope.clone();
+        written.revision = 7;
+        assert_eq!(7, revision(&written.to_bytes().unwrap()));
+        assert_eq!(0, revision(b"[]"));
+        assert_eq!(0, revision(br#"{"schema":"playlists","version":1,"payload":[]}"#));
+    }
+
+    #[test]
+    fn legacy_and_future_versions() {
+        let legacy = format!("[{}]", SONG);
+        let envelope = Envelope::from_bytes(legacy.as_bytes(), Schema::Library).unwrap();
+        assert_eq!(Schema::Library.current_version(), envelope.version);
+        assert_eq!(None, envelope.writer);
+
+        let mut future = Envelope::new(Schema::Library, "[]", "0xabc".into()).unwrap();
+        future.version += 1;
+        assert!(matches!(
+            Envelope::from_bytes(&future.to_bytes().unwrap(), Schema::Library),
+            Err(Error::InvalidDocument(_))
+        ));
+    }
+}
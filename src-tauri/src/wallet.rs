@@ -0,0 +1,141 @@
+use crate::account_name::AccountName;
+use crate::Error;
+use std::time::{Duration, Instant};
+
+pub(crate) const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+// Tracks who is signed in to the Safe client, and whether the wallet is locked after inactivity.
+pub(crate) struct WalletState {
+    pub(crate) login: Option<AccountName>,
+    pub(crate) watch_only: bool,        // signed in without a key
+    pub(crate) watched: Option<String>, // EVM address whose registers are read, if watch-only
+    pub(crate) locked: bool,
+    pub(crate) lock_timeout: Option<Duration>, // None - never lock
+    last_activity: Instant,
+    eth_pk: Option<String>, // to sign in again after reconnecting, dropped when locked
+}
+
+impl Default for WalletState {
+    fn default() -> Self {
+        WalletState {
+            login: None,
+            watch_only: false,
+            watched: None,
+            locked: false,
+            lock_timeout: Some(DEFAULT_LOCK_TIMEOUT),
+            last_activity: Instant::now(),
+            eth_pk: None,
+        }
+    }
+}
+
+impl WalletState {
+    pub(crate) fn signed_in(&mut self, login: AccountName, eth_pk: String) {
+        self.login = Some(login);
+        self.watch_only = false;
+        self.watched = None;
+        self.unlocked(eth_pk);
+    }
+
+    pub(crate) fn signed_in_watch_only(&mut self, login: AccountName, address: String) {
+        self.login = Some(login);
+        self.watch_only = true;
+        self.watched = Some(address);
+        self.locked = false;
+        self.eth_pk = None;
+    }
+
+    pub(crate) fn signed_out(&mut self) {
+        self.login = None;
+        self.watch_only = false;
+        self.watched = None;
+        self.locked = false;
+        self.eth_pk = None;
+    }
+
+    pub(crate) fn mark_locked(&mut self) {
+        self.locked = true;
+        self.eth_pk = None;
+    }
+
+    pub(crate) fn unlocked(&mut self, eth_pk: String) {
+        self.locked = false;
+        self.eth_pk = Some(eth_pk);
+        self.touch();
+    }
+
+    // key of signed in and unlocked user
+    pub(crate) fn eth_pk(&self) -> Option<String> {
+        self.eth_pk.clone()
+    }
+
+    pub(crate) fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    // to be called before every operation that spends from the wallet
+    pub(crate) fn ensure_can_pay(&mut self) -> Result<(), Error> {
+        if self.watch_only {
+            return Err(Error::WatchOnly);
+        }
+        if self.locked {
+            return Err(Error::Locked);
+        }
+        self.touch();
+        Ok(())
+    }
+
+    pub(crate) fn should_lock(&self, now: Instant) -> bool {
+        self.login.is_some()
+            && !self.watch_only
+            && !self.locked
+            && self
+                .lock_timeout
+                .is_some_and(|timeout| now.duration_since(self.last_activity) >= timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locking() {
+        let mut wallet = WalletState::default();
+        let later = Instant::now() + DEFAULT_LOCK_TIMEOUT;
+        assert!(!wallet.should_lock(later)); // nobody signed in
+
+        wallet.signed_in(AccountName::parse("alice").unwrap(), "pk".into());
+        assert!(!wallet.should_lock(Instant::now()));
+        assert!(wallet.should_lock(Instant::now() + DEFAULT_LOCK_TIMEOUT));
+
+        wallet.mark_locked();
+        assert!(!wallet.should_lock(Instant::now() + DEFAULT_LOCK_TIMEOUT)); // already locked
+        assert!(matches!(wallet.ensure_can_pay(), Err(Error::Locked)));
+        assert_eq!(None, wallet.eth_pk());
+
+        wallet.unlocked("pk".into());
+        assert!(wallet.ensure_can_pay().is_ok());
+        assert_eq!(Some(String::from("pk")), wallet.eth_pk());
+
+        wallet.lock_timeout = None;
+        assert!(!wallet.should_lock(Instant::now() + DEFAULT_LOCK_TIMEOUT * 10));
+    }
+
+    #[test]
+    fn watch_only() {
+        let mut wallet = WalletState::default();
+        wallet.signed_in_watch_only(AccountName::parse("label").unwrap(), "0xabc".into());
+        assert!(matches!(wallet.ensure_can_pay(), Err(Error::WatchOnly)));
+        assert_eq!(Some(String::from("0xabc")), wallet.watched);
+        assert!(!wallet.should_lock(Instant::now() + DEFAULT_LOCK_TIMEOUT)); // nothing to lock
+        assert_eq!(None, wallet.eth_pk());
+
+        wallet.signed_in(AccountName::parse("alice").unwrap(), "pk".into());
+        assert!(wallet.ensure_can_pay().is_ok());
+        assert_eq!(None, wallet.watched);
+
+        wallet.signed_out();
+        assert_eq!(None, wallet.eth_pk());
+    }
+}
@@ -13,25 +13,82 @@ enum LogLevel {
     ERROR,
 }
 
-export async function listAccounts(): Promise<[string, string][] | null> {
+// corresponds to Error in Rust, as thrown by invoke
+export type BackendError = {
+    code: ErrorCode;
+    message: string; // human readable
+    details?: Record<string, unknown>; // e.g. path for FileIo
+};
+
+export type ErrorCode =
+    | "Common"
+    | "BadLogin"
+    | "BadPassword"
+    | "BadAccountName"
+    | "BadMnemonic"
+    | "NotConnected"
+    | "AlreadyConnecting"
+    | "ConnectTimeout"
+    | "ConnectCancelled"
+    | "Disconnecting"
+    | "Aborted"
+    | "Locked"
+    | "WatchOnly"
+    | "InsufficientFunds"
+    | "InsufficientGas"
+    | "NotFound"
+    | "Timeout"
+    | "InvalidAddress"
+    | "FileIo"
+    | "TagRead"
+    | "TagWrite"
+    | "Network"
+    | "InvalidDocument"
+    | "Conflict" // details: revision and current RegisterDocument
+    | "WrongAccount" // private register, details: address of its owner
+    | "NotSupported" // e.g. registers of a watched account on a real network
+    | "AccountExists" // details: login
+    | "AlreadyImported" // the same account, details: login
+    | "BundleMismatch"; // details: address of the bundle and keyAddress of its key
+
+export function isBackendError(e: unknown): e is BackendError {
+    return typeof e === "object" && e !== null && "code" in e && "message" in e;
+}
+
+// corresponds to AccountSummary in Rust
+export type AccountSummary = {
+    username: string;
+    address: string | null;
+    watchOnly: boolean; // account without a key, cannot pay for anything
+    displayName: string;
+    createdAt: number; // seconds since UNIX epoch
+    lastSignInAt: number | null;
+    preferredNetwork: NetworkSetting | null;
+    avatar: string | null;
+};
+
+// sorted from most recently used
+export async function listAccounts(): Promise<AccountSummary[] | null> {
     try {
-        return await invoke<[string, string][]>("list_accounts");
+        return await invoke<AccountSummary[]>("list_accounts");
     } catch (e) {
         console.error("listAccounts: ", e);
     }
     return null;
 }
 
-// if peer is a Multiaddr, it will connect to local network.
-// leave peer empty or anything other than Multiaddr to connect to official network.
-export async function connectInner(peer?: string): Promise<boolean> {
+// corresponds to NetworkSetting in Rust
+export type NetworkSetting =
+    | { network: "mainnet" }
+    | { network: "alpha" }
+    | { network: "custom"; peers: string[] } // bootstrap Multiaddrs
+    | { network: "mock" }; // in-memory, for offline development
+
+// leave network empty to connect to the last used one (mainnet at first).
+export async function connectInner(network?: NetworkSetting): Promise<boolean> {
     console.log("connecting...");
     try {
-        if (peer) {
-            await invoke("connect", { peer: peer });
-        } else {
-            await invoke("connect");
-        }
+        await invoke("connect", { network: network ?? null });
         console.log("connected.");
         return true;
     } catch (e) {
@@ -40,6 +97,41 @@ export async function connectInner(peer?: string): Promise<boolean> {
     return false;
 }
 
+export async function networkSetting(): Promise<NetworkSetting | null> {
+    try {
+        return await invoke<NetworkSetting>("network_setting");
+    } catch (e) {
+        console.error("networkSetting: ", e);
+    }
+    return null;
+}
+
+// chooses network for the next connect, without connecting
+export async function setNetwork(network: NetworkSetting): Promise<boolean> {
+    try {
+        await invoke("set_network", { network: network });
+        return true;
+    } catch (e) {
+        console.error("setNetwork: ", e);
+    }
+    return false;
+}
+
+// Aborts connecting in progress, "connect_failed" event follows.
+export async function cancelConnect(): Promise<boolean> {
+    try {
+        await invoke("cancel_connect");
+        return true;
+    } catch (e) {
+        console.error("cancelConnect: ", e);
+    }
+    return false;
+}
+
+export async function setConnectTimeout(seconds: number): Promise<void> {
+    await invoke("set_connect_timeout", { seconds: seconds });
+}
+
 // Finds user folder in storage by username,
 // and decrypts key with the password
 export async function signIn(
@@ -63,24 +155,163 @@ export async function signIn(
 
 // Creates user folder in storage
 // and encrypts Private Key with the password and stores in the folder
+// Creates an account with a key derived from a new mnemonic phrase, unless ethPkImport is given.
+// Returns the phrase, which has to be shown to the user, as it is not stored anywhere
+// (null when a key was imported), or undefined on failure.
 export async function register(
     username: string,
     password: string,
-    ethPkImport?: string // if you want to register an account with particular privkey
-): Promise<boolean> {
+    ethPkImport?: string, // if you want to register an account with particular privkey
+    words?: 12 | 24
+): Promise<string | null | undefined> {
     console.log("registering...");
     try {
-        await invoke("sign_in", {
+        const mnemonic = await invoke<string | null>("sign_in", {
             login: username,
             password: password,
             register: true,
             ethPkImport: ethPkImport,
+            mnemonicWords: words,
         });
         console.log("registered.");
-        return true;
+        return mnemonic;
     } catch (e) {
         console.error("register: ", e);
     }
+    return undefined;
+}
+
+// Creates an account with a key derived from existing mnemonic phrase.
+export async function recoverAccount(
+    username: string,
+    password: string,
+    mnemonic: string
+): Promise<boolean> {
+    console.log("recovering account...");
+    try {
+        await invoke("recover_account", {
+            login: username,
+            password: password,
+            mnemonic: mnemonic,
+        });
+        console.log("account recovered.");
+        return true;
+    } catch (e) {
+        console.error("recoverAccount: ", e);
+    }
+    return false;
+}
+
+// Adds an account with just an address, for browsing other user's public data.
+// Their registers can be read only on the mock network, others fail with NotSupported.
+export async function addWatchOnlyAccount(
+    username: string,
+    address: string,
+    displayName?: string
+): Promise<boolean> {
+    try {
+        await invoke("add_watch_only_account", {
+            login: username,
+            address: address,
+            displayName: displayName,
+        });
+        return true;
+    } catch (e) {
+        console.error("addWatchOnlyAccount: ", e);
+    }
+    return false;
+}
+
+export async function signInWatchOnly(username: string): Promise<boolean> {
+    console.log("signing in watch-only...");
+    try {
+        await invoke("sign_in_watch_only", { login: username });
+        console.log("signed in watch-only.");
+        return true;
+    } catch (e) {
+        console.error("signInWatchOnly: ", e);
+    }
+    return false;
+}
+
+// Removes user's wallet from the client and clears the session.
+export async function signOut(): Promise<boolean> {
+    console.log("signing out...");
+    try {
+        await invoke("sign_out");
+        console.log("signed out.");
+        return true;
+    } catch (e) {
+        console.error("signOut: ", e);
+    }
+    return false;
+}
+
+// Wallet gets locked after inactivity ("locked" event), unlock it with user password.
+export async function unlock(password: string): Promise<boolean> {
+    try {
+        await invoke("unlock", { password: password });
+        return true;
+    } catch (e) {
+        console.error("unlock: ", e);
+    }
+    return false;
+}
+
+export async function isLocked(): Promise<boolean> {
+    return await invoke<boolean>("is_locked");
+}
+
+// null to never lock
+export async function setLockTimeout(seconds: number | null): Promise<void> {
+    await invoke("set_lock_timeout", { seconds: seconds });
+}
+
+// corresponds to ConnectionState in Rust,
+// also sent with "connection_state_changed" event
+export type ConnectionState =
+    | { state: "disconnected" }
+    | { state: "connecting" }
+    | { state: "connected" }
+    | { state: "failed"; reason: string }
+    | { state: "reconnecting" }
+    | { state: "disconnecting" };
+
+export async function connectionStatus(): Promise<ConnectionState> {
+    return await invoke<ConnectionState>("connection_status");
+}
+
+// corresponds to NetworkDiagnostics in Rust
+export type NetworkDiagnostics = {
+    network: NetworkSetting;
+    state: ConnectionState;
+    uptimeSecs: number | null; // null when not connected
+    bootstrapPeers: number | null; // null when the client picks them itself
+    lastSuccess: {
+        operation: string;
+        at: number; // seconds since UNIX epoch
+        latencyMs: number;
+    } | null;
+    recentFailures: Record<string, Record<string, number>>; // by operation and ErrorCode, within the last hour
+};
+
+export async function networkDiagnostics(): Promise<NetworkDiagnostics | null> {
+    try {
+        return await invoke<NetworkDiagnostics>("network_diagnostics");
+    } catch (e) {
+        console.error("networkDiagnostics: ", e);
+    }
+    return null;
+}
+
+// saves JSON report to attach to a bug report
+export async function exportDiagnostics(destination: string): Promise<boolean> {
+    try {
+        await invoke("export_diagnostics", { destination: destination });
+        return true;
+    } catch (e) {
+        console.error("exportDiagnostics: ", e);
+    }
     return false;
 }
 
@@ -101,10 +332,16 @@ export async function isConnected(): Promise<boolean> {
     return false;
 }
 
-export async function disconnect(): Promise<boolean> {
+// payload of "disconnected" event
+export type Disconnected = {
+    aborted: string[]; // operations that did not finish in time
+};
+
+// Waits up to timeoutSecs (default 10) for transfers in progress, then aborts them.
+export async function disconnect(timeoutSecs?: number): Promise<boolean> {
     console.log("disconnecting...");
     try {
-        await invoke("disconnect");
+        await invoke("disconnect", { timeoutSecs: timeoutSecs ?? null });
         console.log("disconnected.");
         return true;
     } catch (e) {
@@ -147,10 +384,71 @@ export async function privateKey(
     return null;
 }
 
-export async function deleteAccount(username: string): Promise<boolean> {
+export async function changePassword(
+    username: string,
+    oldPassword: string,
+    newPassword: string
+): Promise<boolean> {
+    console.log("changing password...");
+    try {
+        await invoke("change_password", {
+            login: username,
+            oldPassword: oldPassword,
+            newPassword: newPassword,
+        });
+        console.log("password changed.");
+        return true;
+    } catch (e) {
+        console.error("changePassword: ", e);
+    }
+    return false;
+}
+
+// Saves account into a single password-protected file.
+export async function exportAccount(
+    username: string,
+    password: string,
+    destination: string // bundle file path
+): Promise<boolean> {
+    try {
+        await invoke("export_account", {
+            login: username,
+            password: password,
+            destination: destination,
+        });
+        return true;
+    } catch (e) {
+        console.error("exportAccount: ", e);
+    }
+    return false;
+}
+
+// Returns [username, address] of imported account.
+export async function importAccount(
+    file: string, // bundle file path
+    password: string,
+    username?: string // import under a different name
+): Promise<[string, string] | null> {
+    try {
+        return await invoke<[string, string]>("import_account", {
+            file: file,
+            password: password,
+            login: username,
+        });
+    } catch (e) {
+        console.error("importAccount: ", e);
+    }
+    return null;
+}
+
+// Account is moved to trash and can be restored for some time.
+export async function deleteAccount(
+    username: string,
+    password?: string // not needed for watch-only accounts
+): Promise<boolean> {
     console.log("deleting account...");
     try {
-        await invoke("delete_account", { login: username });
+        await invoke("delete_account", { login: username, password: password });
         console.log("account deleted.");
         return true;
     } catch (e) {
@@ -159,6 +457,38 @@ export async function deleteAccount(username: string): Promise<boolean> {
     return false;
 }
 
+// corresponds to TrashEntry in Rust
+export type DeletedAccount = {
+    id: string;
+    login: string;
+    deletedAt: number; // seconds since UNIX epoch
+};
+
+export async function listDeletedAccounts(): Promise<DeletedAccount[] | null> {
+    try {
+        return await invoke<DeletedAccount[]>("list_deleted_accounts");
+    } catch (e) {
+        console.error("listDeletedAccounts: ", e);
+    }
+    return null;
+}
+
+// returns username of restored account
+export async function restoreDeletedAccount(
+    id: string,
+    username?: string // if original name is already taken
+): Promise<string | null> {
+    try {
+        return await invoke<string>("restore_deleted_account", {
+            id: id,
+            login: username,
+        });
+    } catch (e) {
+        console.error("restoreDeletedAccount: ", e);
+    }
+    return null;
+}
+
 export async function sessionRead(key: string): Promise<string | null> {
     return await invoke("session_read", { key: key });
 }
@@ -182,21 +512,64 @@ export async function logLevel(level: keyof typeof LogLevel): Promise<boolean> {
     return false;
 }
 
+// Backend log event, streamed as it happens.
+export type BackendLog = {
+    level: string;
+    target: string;
+    message: string;
+};
+
+// Zips backend log files to destination, for bug reports.
+export async function exportLogs(destination: string): Promise<boolean> {
+    try {
+        await invoke("export_logs", { destination: destination });
+        return true;
+    } catch (e) {
+        console.error("exportLogs: ", e);
+    }
+    return false;
+}
+
 function prepareMeta(name: string[]): string[] {
     name.unshift(REG_META_PREFIX);
     return name;
 }
 
+// Document kinds kept in registers, checked by backend against Rust types.
+export type RegisterSchema = "playlists" | "library" | "profile";
+
+// corresponds to PublicProfile in Rust, payload of a "profile" register
+export type PublicProfile = {
+    displayName: string;
+    bio?: string | null;
+    avatar?: string | null; // xorname of the picture
+};
+
+// corresponds to Envelope in Rust, what a register stores
+export type RegisterDocument = {
+    schema: RegisterSchema;
+    version: number; // of the schema
+    revision: number; // 0 when created, +1 on each write, pass to writeReg as expectedRevision
+    writer: string | null; // EVM address, null for registers from before envelopes
+    writtenAt: number; // seconds since UNIX epoch, 0 if unknown
+    encrypted: boolean; // private, readable only by the writer account
+    payload: any;
+};
+
 export async function createReg(
     name: string[],
-    data?: object
+    schema: RegisterSchema,
+    data?: object,
+    isPrivate: boolean = false // encrypted with a key of signed in account
 ): Promise<boolean> {
     prepareMeta(name);
     console.log("creating Reg: " + name + "...");
     try {
         await invoke("create_reg", {
             name: name,
+            schema: schema,
             data: typeof data === "undefined" ? "" : JSON.stringify(data),
+            private: isPrivate,
         });
 
         console.log("created Reg.");
@@ -208,37 +581,140 @@ export async function createReg(
     return false;
 }
 
-export async function readReg(name: string[]): Promise<object | null> {
+export async function readReg(
+    name: string[],
+    schema: RegisterSchema
+): Promise<RegisterDocument | null> {
     prepareMeta(name);
     console.log("reading Reg: " + name + "...");
 
     try {
-        return JSON.parse(await invoke("read_reg", { name: name }));
+        return await invoke<RegisterDocument>("read_reg", {
+            name: name,
+            schema: schema,
+        });
     } catch (e) {
         console.error("readReg: ", e);
     }
     return null;
 }
 
-export async function writeReg(name: string[], data: object): Promise<boolean> {
+// Throws BackendError with "Conflict" code, if register moved past expectedRevision.
+export async function writeReg(
+    name: string[],
+    schema: RegisterSchema,
+    data: object,
+    expectedRevision?: number, // revision of RegisterDocument the data is based on
+    isPrivate: boolean = false // keep as it was created
+): Promise<boolean> {
     prepareMeta(name);
     console.log("writing Reg: " + name + "...");
 
     try {
         await invoke("write_reg", {
             name: name,
+            schema: schema,
             data: JSON.stringify(data),
+            private: isPrivate,
+            expectedRevision: expectedRevision ?? null,
         });
 
         console.log("written Reg.");
         console.log(await balance());
         return true;
     } catch (e) {
+        if (isBackendError(e) && e.code === "Conflict") {
+            throw e;
+        }
         console.error("writeReg: ", e);
     }
     return false;
 }
 
+// Three-way merge of register documents, null if changes conflict or on error.
+export async function mergeDocuments(
+    schema: RegisterSchema,
+    base: object,
+    ours: object,
+    theirs: object
+): Promise<any | null> {
+    try {
+        return await invoke("merge_documents", {
+            schema: schema,
+            base: JSON.stringify(base),
+            ours: JSON.stringify(ours),
+            theirs: JSON.stringify(theirs),
+        });
+    } catch (e) {
+        console.error("mergeDocuments: ", e);
+    }
+    return null;
+}
+
+// corresponds to RegisterVersion in Rust
+export type RegisterVersion = {
+    version: number; // 0 is the first value
+    writer: string | null;
+    writtenAt: number; // seconds since UNIX epoch, 0 if unknown
+    size: number; // bytes
+};
+
+// corresponds to RegisterHistory in Rust
+export type RegisterHistory = {
+    versions: RegisterVersion[]; // oldest first
+    complete: boolean; // false if too long, newest versions are missing
+};
+
+export async function regHistory(
+    name: string[]
+): Promise<RegisterHistory | null> {
+    prepareMeta(name);
+    try {
+        return await invoke<RegisterHistory>("reg_history", { name: name });
+    } catch (e) {
+        console.error("regHistory: ", e);
+    }
+    return null;
+}
+
+export async function readRegVersion(
+    name: string[],
+    schema: RegisterSchema,
+    version: number
+): Promise<RegisterDocument | null> {
+    prepareMeta(name);
+    try {
+        return await invoke<RegisterDocument>("read_reg_version", {
+            name: name,
+            schema: schema,
+            version: version,
+        });
+    } catch (e) {
+        console.error("readRegVersion: ", e);
+    }
+    return null;
+}
+
+// writes a past value back as the newest one
+export async function restoreRegVersion(
+    name: string[],
+    schema: RegisterSchema,
+    version: number
+): Promise<boolean> {
+    prepareMeta(name);
+    try {
+        await invoke("restore_reg_version", {
+            name: name,
+            schema: schema,
+            version: version,
+        });
+        return true;
+    } catch (e) {
+        console.error("restoreRegVersion: ", e);
+    }
+    return false;
+}
+
 // returns xorname address
 export async function uploadFile(
     path: string // filesystem path
@@ -263,6 +739,90 @@ export async function putData(
     return null;
 }
 
+// corresponds to QueueItem in Rust
+export type QueueItem = {
+    id: number;
+    login: string; // replayed only when this account is signed in
+    createdAt: number; // seconds since UNIX epoch
+    attempts: number;
+    lastError: string | null;
+} & (
+    | { kind: "upload"; file: string }
+    | { kind: "putData"; size: number }
+    | {
+          kind: "writeReg";
+          name: string[];
+          schema: RegisterSchema;
+          data: string; // JSON, or hex of it encrypted if private
+          private: boolean;
+      }
+);
+
+// payload of "queue_item_done" and "queue_item_failed" events
+export type QueueItemResult = {
+    id: number;
+    xorname: string | null; // of uploaded data
+    error: string | null;
+};
+
+// Offline queue, items are replayed in order after connecting.
+export async function queueUpload(path: string): Promise<QueueItem | null> {
+    try {
+        return await invoke<QueueItem>("queue_upload", { file: path });
+    } catch (e) {
+        console.error("queueUpload: ", e);
+    }
+    return null;
+}
+
+export async function queuePutData(data: Uint8Array): Promise<QueueItem | null> {
+    try {
+        return await invoke<QueueItem>("queue_put_data", { data: data });
+    } catch (e) {
+        console.error("queuePutData: ", e);
+    }
+    return null;
+}
+
+export async function queueWriteReg(
+    name: string[],
+    schema: RegisterSchema,
+    data: object,
+    isPrivate: boolean = false
+): Promise<QueueItem | null> {
+    prepareMeta(name);
+    try {
+        return await invoke<QueueItem>("queue_write_reg", {
+            name: name,
+            schema: schema,
+            data: JSON.stringify(data),
+            private: isPrivate,
+        });
+    } catch (e) {
+        console.error("queueWriteReg: ", e);
+    }
+    return null;
+}
+
+export async function listQueue(): Promise<QueueItem[] | null> {
+    try {
+        return await invoke<QueueItem[]>("list_queue");
+    } catch (e) {
+        console.error("listQueue: ", e);
+    }
+    return null;
+}
+
+export async function cancelQueued(id: number): Promise<boolean> {
+    try {
+        await invoke("cancel_queued", { id: id });
+        return true;
+    } catch (e) {
+        console.error("cancelQueued: ", e);
+    }
+    return false;
+}
+
 export async function download(
     xorname: string,
     destinationDir: string,
@@ -1,7 +1,10 @@
 import {
     connectInner,
+    NetworkSetting,
     signIn as autonomiSignIn,
+    signOut as autonomiSignOut,
     register,
+    recoverAccount,
     clientAddress,
     isConnected,
     listAccounts,
@@ -15,15 +18,13 @@ import {
     SimpleAccountUser,
     RecoverAccountUser,
 } from "@/types/account-user";
-import {
-    getDownloadFolder,
-    getSelectedNetwork,
-    getTestnetPeerAddress,
-} from "@/backend/backend-store";
+import { getDownloadFolder } from "@/backend/backend-store";
 import Networks from "@/enums/networks";
 import { isEthereumAddress } from "@/lib/utils/address";
+import { parsePeerAddresses } from "@/lib/utils/network";
 import { NetworkFileDetail } from "@/types/network-file-detail";
 import { filePictureToDataURL } from "@/lib/utils/images";
+import { isRecoveryPhrase } from "@/lib/utils/validation";
 
 // =======
 // This file contains higher-level backend code with some application logic, and can use frontend types.
@@ -37,28 +38,23 @@ export async function connect(override?: {
 }): Promise<boolean> {
     console.log("connecting...");
     try {
-        let peer = undefined;
-        let network = undefined;
+        // without a network picked by the user, the backend connects to the last used one
+        let setting: NetworkSetting | undefined = undefined;
 
         // this is used if connecting from the disconnected-panel component
         if (override && override.network) {
-            network = override.network;
-            if (network == Networks.TESTNET) {
-                peer = override.peer;
-            }
-        } else {
-            network = await getSelectedNetwork();
-            if (network == Networks.TESTNET) {
-                peer = await getTestnetPeerAddress();
+            const peer =
+                override.network == Networks.TESTNET ? override.peer : undefined;
+            if (override.network == Networks.TESTNET && !peer) {
+                console.error("Peer not supplied for TESTNET.");
+                return false;
             }
+            setting = peer
+                ? { network: "custom", peers: parsePeerAddresses(peer) }
+                : { network: "mainnet" };
         }
 
-        if (network == Networks.TESTNET && !peer) {
-            console.error("Peer not supplied for TESTNET.");
-            return false;
-        }
-
-        const success = await connectInner(peer ?? undefined);
+        const success = await connectInner(setting);
         if (success) {
             console.log("connected.");
             return true;
@@ -69,24 +65,20 @@ export async function connect(override?: {
     return false;
 }
 
+// mnemonic is the recovery phrase of a new key, to be shown to the user
 export async function registerUser(
     newUser: RegisterAccountUser | RecoverAccountUser
-): Promise<AccountUser | null> {
+): Promise<(AccountUser & { mnemonic: string | null }) | null> {
     console.log(`Attempting to create a new user: ${newUser.username}`);
 
     try {
         // Register and connect the user
-        let success = false;
-        if ((newUser as RecoverAccountUser).privateKey !== undefined) {
-            success = await register(
-                newUser.username,
-                newUser.password,
-                (newUser as RecoverAccountUser).privateKey
-            );
-        } else {
-            success = await register(newUser.username, newUser.password);
-        }
-        if (!success) {
+        const mnemonic = await register(
+            newUser.username,
+            newUser.password,
+            (newUser as RecoverAccountUser).privateKey
+        );
+        if (mnemonic === undefined) {
             return null;
         }
 
@@ -109,7 +101,7 @@ export async function registerUser(
             return null;
         }
 
-        const registeredUser = { ...newUser, address };
+        const registeredUser = { ...newUser, address, mnemonic };
         registeredUser.password = ""; // we cannot save passwords
 
         console.log(`User ${newUser.username} created successfully.`);
@@ -123,6 +115,32 @@ export async function registerUser(
     }
 }
 
+// Recovers from a recovery phrase, or registers with the raw private key.
+export async function recoverUser(
+    user: RecoverAccountUser
+): Promise<AccountUser | null> {
+    if (!isRecoveryPhrase(user.privateKey)) {
+        return await registerUser(user);
+    }
+
+    const recovered = await recoverAccount(
+        user.username,
+        user.password,
+        user.privateKey.trim()
+    );
+    const address = recovered ? await clientAddress() : null;
+    if (address === null || !isEthereumAddress(address)) {
+        console.error(`Failed to recover account: ${user.username}`);
+        return null;
+    }
+    return {
+        username: user.username,
+        address,
+        dateCreated: user.dateCreated,
+        dateUpdated: user.dateUpdated,
+    };
+}
+
 export async function signIn(
     username: string,
     password: string
@@ -146,11 +164,7 @@ export async function signIn(
 }
 
 export async function signOut(): Promise<void> {
-    try {
-        await sessionSet(USER_SESSION_KEY, null);
-    } catch (e) {
-        console.error("signOut: ", e);
-    }
+    await autonomiSignOut();
 }
 
 export async function saveUser(user: AccountUser) {
@@ -183,21 +197,21 @@ export async function getConnectedUserAccount(): Promise<AccountUser | null> {
 
 // Return all registered accounts with addresses, sorted from most recently used.
 export async function registeredAccounts(): Promise<SimpleAccountUser[]> {
-    // Fetch the accounts as an array of [username, address] tuples
     const accounts = await listAccounts();
 
-    // If the accounts are not null, map the tuples to SimpleAccountUser objects
-    // Assuming accounts is an array of tuples [username, address]
     if (accounts) {
-        // Reverse the accounts array
-        accounts.reverse();
-
-        // Filter out invalid Ethereum addresses and then map the results
+        // Filter out invalid Ethereum addresses and watch-only accounts,
+        // which can't sign in with a password, and then map the results
         return accounts
-            .filter(([_username, address]) => isEthereumAddress(address)) // Filter valid addresses
-            .map(([username, address]) => ({
-                username,
-                address,
+            .filter(
+                (account) =>
+                    !account.watchOnly &&
+                    account.address !== null &&
+                    isEthereumAddress(account.address)
+            )
+            .map((account) => ({
+                username: account.username,
+                address: account.address as string,
             }));
     } else {
         return [];
@@ -1,4 +1,4 @@
-import { uploadFile } from "@/backend/autonomi";
+import { isBackendError, uploadFile } from "@/backend/autonomi";
 import { Errors } from "@/enums/errors";
 
 export async function uploadSong(
@@ -17,10 +17,10 @@ export async function uploadSong(
         } else {
             return { success: false };
         }
-    } catch (e: any) {
-        const errorMessage = e.Common?.toLowerCase().trim().replace(/\.$/, ""); // convert to lowercase and remove trailing period
+    } catch (e: unknown) {
+        const code = isBackendError(e) ? e.code : null;
 
-        if (errorMessage?.includes("error occurred during payment")) {
+        if (code === "InsufficientFunds" || code === "InsufficientGas") {
             return { success: false, error: Errors.PaymentRequired };
         } else {
             return { success: false, error: Errors.UnknownError };
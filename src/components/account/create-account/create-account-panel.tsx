@@ -72,12 +72,12 @@ const CreateAccountPanel: React.FC<CreateAccountPanelProps> = ({
                     >
                         <AlertDialogContent>
                             <AlertDialogHeader>
-                                <AlertDialogTitle>Private Key</AlertDialogTitle>
+                                <AlertDialogTitle>Recovery Phrase</AlertDialogTitle>
                                 <AlertDialogDescription>
                                     <p className="pb-4">
-                                        Your private key can be used to recover
-                                        your account. Please save it to a secure
-                                        place.
+                                        Your recovery phrase can be used to
+                                        recover your account. Please save it to
+                                        a secure place.
                                     </p>
                                     <div className="flex items-center space-x-2">
                                         <div className="flex-grow rounded-lg bg-secondary px-2 py-1 text-sm tracking-tighter">
@@ -86,7 +86,7 @@ const CreateAccountPanel: React.FC<CreateAccountPanelProps> = ({
                                             </span>{" "}
                                         </div>
                                         <div
-                                            title="Copy Private Key"
+                                            title="Copy Recovery Phrase"
                                             className="flex cursor-pointer items-center transition"
                                             onClick={() => {
                                                 if (secret) {
@@ -165,7 +165,10 @@ const CreateAccountPanel: React.FC<CreateAccountPanelProps> = ({
                 return;
             }
 
-            const secret = await privateKey(result.username, data.password);
+            // a new key always gets a phrase, the key itself is the fallback
+            const secret =
+                result.mnemonic ??
+                (await privateKey(result.username, data.password));
             await showSecretDialog(secret);
 
             try {
@@ -202,7 +205,7 @@ const CreateAccountPanel: React.FC<CreateAccountPanelProps> = ({
             }
 
             const foundAccount = accounts.find(
-                ([accountUsername]) => accountUsername === username
+                (account) => account.username === username
             );
 
             if (foundAccount) {
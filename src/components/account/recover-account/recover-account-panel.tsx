@@ -14,7 +14,7 @@ import { zodResolver } from "@hookform/resolvers/zod";
 import { recoverAccountSchema } from "@/form-schemas/recover-account-schema";
 import { Input } from "@/components/ui/input";
 import { RecoverAccountUser } from "@/types/account-user";
-import { registerUser } from "@/backend/logic";
+import { recoverUser } from "@/backend/logic";
 import { useTranslation } from "react-i18next";
 import { useState } from "react";
 import { listAccounts } from "@/backend/autonomi";
@@ -66,8 +66,8 @@ const RecoverAccountPanel: React.FC<RecoverAccountPanelProps> = ({
             dateUpdated: new Date(),
         };
 
-        // Proceed with account creation
-        registerUser(newUser);
+        // Proceed with account creation, from the phrase if one was entered
+        recoverUser(newUser);
     };
 
     const handleReturnToSignInPanelClicked = () => {
@@ -84,7 +84,7 @@ const RecoverAccountPanel: React.FC<RecoverAccountPanelProps> = ({
             }
 
             const foundAccount = accounts.find(
-                ([accountUsername]) => accountUsername === username
+                (account) => account.username === username
             );
 
             if (foundAccount) {
@@ -124,9 +124,7 @@ const RecoverAccountPanel: React.FC<RecoverAccountPanelProps> = ({
                                     <FormLabel>{t("privateKey")}</FormLabel>
                                     <FormControl>
                                         <Input
-                                            placeholder={t(
-                                                "enterYourPrivateKey"
-                                            )}
+                                            placeholder="Enter your private key or recovery phrase"
                                             autoCapitalize="off"
                                             autoComplete="off"
                                             autoCorrect="off"
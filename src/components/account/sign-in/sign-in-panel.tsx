@@ -23,6 +23,16 @@ import { registeredAccounts } from "@/backend/logic";
 import { SimpleAccountUser } from "@/types/account-user";
 import { useTranslation } from "react-i18next";
 import { deleteAccount } from "@/backend/autonomi";
+import {
+    AlertDialog,
+    AlertDialogAction,
+    AlertDialogCancel,
+    AlertDialogContent,
+    AlertDialogDescription,
+    AlertDialogFooter,
+    AlertDialogHeader,
+    AlertDialogTitle,
+} from "@/components/ui/alert-dialog";
 
 interface SignInPanelProps {
     onCreateAccountClicked: () => void;
@@ -116,8 +126,36 @@ const SignInPanel: React.FC<SignInPanelProps> = ({
         fetchRecentAccounts();
     }, []);
 
+    // account waiting for its password to be deleted
+    const [accountToDelete, setAccountToDelete] = useState<string | null>(
+        null
+    );
+    const [deletePassword, setDeletePassword] = useState("");
+
     const deleteSelectedAccount = (username: string) => {
-        deleteAccount(username);
+        setDeletePassword("");
+        setAccountToDelete(username);
+    };
+
+    const confirmDeleteAccount = async () => {
+        const username = accountToDelete;
+        setAccountToDelete(null);
+        if (!username) {
+            return;
+        }
+
+        // watch-only accounts have no password
+        const deleted = await deleteAccount(
+            username,
+            deletePassword || undefined
+        );
+        setDeletePassword("");
+        if (!deleted) {
+            toast("Account Not Deleted", {
+                description: `Enter the password of ${username} to delete it`,
+            });
+            return;
+        }
         setRecentAccountList((prevAccounts) =>
             prevAccounts.filter((account) => account.username !== username)
         );
@@ -283,6 +321,36 @@ const SignInPanel: React.FC<SignInPanelProps> = ({
                     deleteSelectedAccount={deleteSelectedAccount}
                 />
             </TabsContent>
+
+            <AlertDialog
+                open={accountToDelete !== null}
+                onOpenChange={(open) => !open && setAccountToDelete(null)}
+            >
+                <AlertDialogContent>
+                    <AlertDialogHeader>
+                        <AlertDialogTitle>{t("deleteAccount")}</AlertDialogTitle>
+                        <AlertDialogDescription>
+                            Enter the password of {accountToDelete} to delete
+                            it.
+                        </AlertDialogDescription>
+                    </AlertDialogHeader>
+                    <Input
+                        placeholder={t("enterYourPassword")}
+                        type="password"
+                        autoCapitalize="off"
+                        autoComplete="off"
+                        autoCorrect="off"
+                        value={deletePassword}
+                        onChange={(e) => setDeletePassword(e.target.value)}
+                    />
+                    <AlertDialogFooter>
+                        <AlertDialogCancel>Cancel</AlertDialogCancel>
+                        <AlertDialogAction onClick={confirmDeleteAccount}>
+                            {t("deleteAccount")}
+                        </AlertDialogAction>
+                    </AlertDialogFooter>
+                </AlertDialogContent>
+            </AlertDialog>
         </Tabs>
     );
 };
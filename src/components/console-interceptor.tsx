@@ -1,5 +1,7 @@
+import { BackendLog } from "@/backend/autonomi";
 import { FrontendLogs } from "@/enums/frontend-logs";
 import { frontendLogsStore } from "@/store/frontend-logs-store";
+import { listen } from "@tauri-apps/api/event";
 import { useEffect } from "react";
 
 export default function ConsoleInterceptor() {
@@ -37,7 +39,14 @@ export default function ConsoleInterceptor() {
         console.warn = captureLog(FrontendLogs.Warn);
         console.error = captureLog(FrontendLogs.Error);
 
+        // backend logs go straight to the store, not to the console
+        const unlistenBackend = listen<BackendLog>("backend_log", (event) => {
+            const { level, target, message } = event.payload;
+            addLog(`[BACKEND ${level}] ${target}: ${message}`);
+        });
+
         return () => {
+            unlistenBackend.then((unlisten) => unlisten());
             // restore original console methods on unmount
             console.log = originalConsole.log;
             console.warn = originalConsole.warn;
@@ -3,7 +3,7 @@ import { connect } from "@/backend/logic";
 import { Button } from "./ui/button";
 import { CableIcon, GlobeLockIcon, ZapOffIcon } from "lucide-react";
 import { Input } from "./ui/input";
-import { isValidPeerAddress } from "@/lib/utils/network";
+import { areValidPeerAddresses } from "@/lib/utils/network";
 import Networks from "@/enums/networks";
 import { useTranslation } from "react-i18next";
 import { useConnection } from "@/providers/connection-provider";
@@ -71,7 +71,7 @@ export default function DisconnectedPanel() {
                             };
                             connect(override);
                         }}
-                        disabled={!isValidPeerAddress(inputValue)}
+                        disabled={!areValidPeerAddresses(inputValue)}
                         className="w-full max-w-sm mt-4"
                     >
                         <span>{t("connectToTestnet")}</span>
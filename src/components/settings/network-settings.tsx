@@ -19,7 +19,7 @@ import {
 } from "@/components/ui/form";
 import Networks from "@/enums/networks";
 import { motion } from "motion/react";
-import { isValidPeerAddress } from "@/lib/utils/network";
+import { areValidPeerAddresses } from "@/lib/utils/network";
 import { useTranslation } from "react-i18next";
 
 export default function StorageSettings() {
@@ -36,7 +36,7 @@ export default function StorageSettings() {
 
     // Determine if the save button should be enabled based on validation
     const isSaveTestnetPeerAddressEnabled =
-        testnetPeerAddress && isValidPeerAddress(testnetPeerAddress);
+        testnetPeerAddress && areValidPeerAddresses(testnetPeerAddress);
 
     useEffect(() => {
         async function loadSettings() {
@@ -1,6 +1,6 @@
 import { z } from "zod";
 import i18n from "i18next";
-import { isValidPrivateKey } from "@/lib/utils/validation";
+import { isRecoveryPhrase, isValidPrivateKey } from "@/lib/utils/validation";
 
 export const recoverAccountSchema = z
     .object({
@@ -11,14 +11,13 @@ export const recoverAccountSchema = z
                     count: 2,
                 }),
             })
-            .max(64, {
-                message: i18n.t("privateKeyCannotExceedCharacters", {
-                    count: 64,
-                }),
-            })
-            .refine((input) => isValidPrivateKey(input), {
-                message: "Private key must be a valid 64-character hex string",
-            }),
+            .refine(
+                (input) => isValidPrivateKey(input) || isRecoveryPhrase(input),
+                {
+                    message:
+                        "Enter a 64-character hex private key or a 12 or 24 word recovery phrase",
+                }
+            ),
         username: z
             .string()
             .min(2, {
@@ -3,3 +3,12 @@ export const isValidPeerAddress = (address: string): boolean => {
         /^\/?ip4\/(\d{1,3}\.){3}\d{1,3}\/udp\/\d{1,5}\/quic-v1\/p2p\/[A-Za-z0-9]{52}\/?$/;
     return pattern.test(address);
 };
+
+// several bootstrap peers can be separated by commas or whitespace
+export const parsePeerAddresses = (input: string): string[] =>
+    input.split(/[\s,]+/).filter((address) => address.length > 0);
+
+export const areValidPeerAddresses = (input: string): boolean => {
+    const addresses = parsePeerAddresses(input);
+    return addresses.length > 0 && addresses.every(isValidPeerAddress);
+};
@@ -10,6 +10,15 @@ export const isValidPrivateKey = (input: string): boolean => {
     return regex.test(input);
 };
 
+// BIP39 phrase of 12 or 24 words, the words themselves are checked by the backend
+export const isRecoveryPhrase = (input: string): boolean => {
+    const words = input.trim().split(/\s+/);
+    return (
+        (words.length === 12 || words.length === 24) &&
+        words.every((word) => /^[a-z]+$/.test(word))
+    );
+};
+
 export function isIDUnique(id: string, playlists: Playlist[]): boolean {
     return !playlists.some((playlist) =>
         playlist.songs?.some((song) => song.id === id)
@@ -13,7 +13,7 @@ import {
 } from "@/backend/logic";
 import { AccountUser } from "@/types/account-user";
 import { isConnected as checkNetworkConnection } from "@/backend/autonomi";
-import { disconnect } from "@/backend/autonomi";
+import { disconnect, Disconnected } from "@/backend/autonomi";
 import Networks from "@/enums/networks";
 import { useNavigate } from "react-router-dom";
 
@@ -122,6 +122,9 @@ export const ConnectionProvider: React.FC<ConnectionProviderProps> = ({
         let unlistenSignIn: (() => void) | null = null;
         let unlistenConnected: (() => void) | null = null;
         let unlistenDisconnected: (() => void) | null = null;
+        let unlistenReconnecting: (() => void) | null = null;
+        let unlistenReconnected: (() => void) | null = null;
+        let unlistenConnectFailed: (() => void) | null = null;
 
         const setupListeners = async () => {
             unlistenSignIn = await listen("sign_in", async () => {
@@ -140,12 +143,47 @@ export const ConnectionProvider: React.FC<ConnectionProviderProps> = ({
                 await fetchAccount();
             });
 
-            unlistenDisconnected = await listen("disconnected", async () => {
-                console.log("Disconnected event received");
-                setIsConnected(false);
-                setAccount(null);
-                await backendSignOut();
+            unlistenDisconnected = await listen<Disconnected>(
+                "disconnected",
+                async (event) => {
+                    console.log("Disconnected event received");
+                    if (event.payload?.aborted?.length) {
+                        console.warn(
+                            "Aborted by disconnect:",
+                            event.payload.aborted
+                        );
+                    }
+                    setIsConnected(false);
+                    setAccount(null);
+                    await backendSignOut();
+                }
+            );
+
+            // backend lost the connection and is retrying with backoff
+            unlistenReconnecting = await listen<number>(
+                "reconnecting",
+                (event) => {
+                    console.log("Reconnecting, attempt", event.payload);
+                    setIsConnecting(true);
+                }
+            );
+
+            unlistenReconnected = await listen("reconnected", async () => {
+                console.log("Reconnected event received");
+                setIsConnected(true);
+                setIsConnecting(false);
+                await fetchAccount();
             });
+
+            // timed out, cancelled or network error
+            unlistenConnectFailed = await listen<string>(
+                "connect_failed",
+                (event) => {
+                    console.log("Connect failed:", event.payload);
+                    setIsConnected(false);
+                    setIsConnecting(false);
+                }
+            );
         };
 
         setupListeners();
@@ -154,6 +192,9 @@ export const ConnectionProvider: React.FC<ConnectionProviderProps> = ({
             unlistenSignIn?.();
             unlistenConnected?.();
             unlistenDisconnected?.();
+            unlistenReconnecting?.();
+            unlistenReconnected?.();
+            unlistenConnectFailed?.();
         };
     }, []);
 